@@ -0,0 +1,41 @@
+// Regression benchmark for `Universe::iterate` on sparse patterns: one
+// blinker per chunk, for growing numbers of chunks. The time per chunk should
+// stay flat as the map grows; anything that copies the whole map per chunk
+// shows up as a cost that rises with the chunk count.
+//
+//     cargo bench --bench iterate
+
+use std::time::Instant;
+
+use life_rust::chunk::SIZE;
+use life_rust::universe::Universe;
+
+const GENERATIONS: u64 = 20;
+
+fn main() {
+    for &side in &[8, 32, 96] {
+        let mut universe = Universe::new();
+        let centre = SIZE as i32 / 2;
+        for cy in 0..side {
+            for cx in 0..side {
+                let (x, y) = (cx * SIZE as i32 + centre, cy * SIZE as i32 + centre);
+                for dx in -1..=1 {
+                    universe.set_cell(x + dx, y, true);
+                }
+            }
+        }
+        let chunks = universe.map.len();
+        let start = Instant::now();
+        for _ in 0..GENERATIONS {
+            universe.iterate();
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        assert_eq!(universe.population(), 3 * (side * side) as usize);
+        println!(
+            "{:>6} chunks: {:>8.2} ms/generation, {:>6.0} ns/chunk",
+            chunks,
+            elapsed * 1e3 / GENERATIONS as f64,
+            elapsed * 1e9 / (GENERATIONS as f64 * chunks as f64)
+        );
+    }
+}
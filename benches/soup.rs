@@ -0,0 +1,63 @@
+// Steps a dense random soup and reports cell updates per second for several
+// thread counts, checking that they all reach the same result. The chunk size is set by `chunk::Row`;
+// rebuild with a different row type to compare.
+//
+//     cargo bench --bench soup
+
+use std::time::Instant;
+
+use life_rust::chunk::SIZE;
+use life_rust::universe::Universe;
+
+const WIDTH: i32 = 1024;
+const GENERATIONS: u64 = 200;
+
+fn soup() -> Universe {
+    let mut state: u64 = 0x853c_49e6_748f_ea9b;
+    let mut universe = Universe::new();
+    for y in 0..WIDTH {
+        for x in 0..WIDTH {
+            // xorshift64, half the cells alive
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            if state >> 63 == 1 {
+                universe.set_cell(x, y, true);
+            }
+        }
+    }
+    universe
+}
+
+fn main() {
+    let mut threads = vec![1, 2, 4, Universe::default_threads()];
+    threads.sort_unstable();
+    threads.dedup();
+    let mut last: Option<Vec<(i32, i32)>> = None;
+    for &n in threads.iter() {
+        let mut universe = soup();
+        universe.threads = n;
+        let start = Instant::now();
+        let mut updates = 0u64;
+        for _ in 0..GENERATIONS {
+            updates += (universe.map.len() * SIZE * SIZE) as u64;
+            universe.iterate();
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        println!(
+            "{0}x{0} chunks, {1:>2} threads: {2} generations of a {3}x{3} soup in {4:.2}s, {5:.1}M cell updates/s, {6:.0} generations/s",
+            SIZE,
+            n,
+            GENERATIONS,
+            WIDTH,
+            seconds,
+            updates as f64 / seconds / 1e6,
+            GENERATIONS as f64 / seconds,
+        );
+        let cells = universe.cells();
+        if let Some(last) = last {
+            assert_eq!(last, cells, "{} threads changed the result", n);
+        }
+        last = Some(cells);
+    }
+}
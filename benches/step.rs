@@ -0,0 +1,80 @@
+// Compares the per-cell and bit-parallel chunk stepping paths on the same
+// random chunks and halos, checking that they agree bit for bit.
+//
+//     cargo bench --bench step
+
+use std::time::Instant;
+
+use life_rust::chunk::{Chunk, Edges, Row, SIZE};
+use life_rust::rule::Rule;
+
+const INPUTS: usize = 1024;
+const ROUNDS: usize = 10;
+
+// xorshift64, so every run sees the same inputs.
+fn next(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn inputs(seed: u64) -> Vec<(Chunk, Edges)> {
+    let mut state = seed;
+    (0..INPUTS)
+        .map(|_| {
+            let mut rows = [0; SIZE];
+            for row in rows.iter_mut() {
+                *row = next(&mut state) as Row;
+            }
+            let chunk = Chunk::from([0, 0], rows);
+            let edges = Edges {
+                left: next(&mut state) as Row,
+                right: next(&mut state) as Row,
+                top: next(&mut state) as Row,
+                bottom: next(&mut state) as Row,
+                corners: next(&mut state) as u8 & 0xf0,
+            };
+            (chunk, edges)
+        })
+        .collect()
+}
+
+fn run(
+    inputs: &[(Chunk, Edges)],
+    rule: &Rule,
+    step: fn(&mut Chunk, &Edges, &Rule) -> u8,
+) -> (Vec<(Chunk, u8)>, f64) {
+    let mut results = Vec::new();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        results.clear();
+        for (chunk, edges) in inputs.iter() {
+            let mut chunk = chunk.clone();
+            let activations = step(&mut chunk, edges, rule);
+            results.push((chunk, activations));
+        }
+    }
+    let per_chunk = start.elapsed().as_secs_f64() * 1e9 / (ROUNDS * inputs.len()) as f64;
+    (results, per_chunk)
+}
+
+fn main() {
+    for rule in ["B3/S23", "B36/S23", "B3678/S34678", "B2/S"].iter() {
+        let rule: Rule = rule.parse().unwrap();
+        let inputs = inputs(0x9e37_79b9_7f4a_7c15);
+        let (by_cell, old) = run(&inputs, &rule, Chunk::iterate_by_cell);
+        let (parallel, new) = run(&inputs, &rule, Chunk::iterate);
+        for (i, (a, b)) in by_cell.iter().zip(parallel.iter()).enumerate() {
+            assert_eq!(a, b, "paths disagree on input {} under {}", i, rule);
+        }
+        println!(
+            "{:>14}: survive {:>8.1} ns/chunk, bit-parallel {:>6.1} ns/chunk ({:.1}x), {} chunks identical",
+            rule.to_string(),
+            old,
+            new,
+            old / new,
+            inputs.len()
+        );
+    }
+}
@@ -1,57 +1,102 @@
-use kiss3d::nalgebra::{Point2, Point3, Translation2};
+use crate::rule::Neighbourhood;
+use crate::rule::Rule;
+use crate::table::RuleTable;
 
-#[derive(Clone)]
+// One row of a chunk, with x = 0 in the most significant bit. Chunks are
+// square, so the row type also sets the chunk size: u64 gives 64x64 chunks,
+// and any of u8, u16 and u32 work as well.
+pub type Row = u64;
+pub const SIZE: usize = Row::BITS as usize;
+const LAST: u8 = SIZE as u8 - 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Chunk {
-    pub chunk: [u8; 8],
-    nodes: Vec<kiss3d::scene::PlanarSceneNode>,
+    // Live cells, the ones counted as neighbours.
+    pub chunk: [Row; SIZE],
+    // Bit planes holding the state number of every cell in a state above 1
+    // (bit i of the state in `states[i]`), and 0 for dead cells. Live cells
+    // hold their colour there under a multi-colour rule, and 0 otherwise.
+    // Empty unless the rule has more than two states or colours.
+    pub states: Vec<[Row; SIZE]>,
     pub active: bool,
-    pub bit_size: f32,
     pub pos: [i32; 2],
-    center: (f32, f32),
-    relative_pos: (f32, f32),
 }
 impl Chunk {
-    const COLORS: (Point3<f32>, Point3<f32>) =
-        (Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0));
-    pub fn new(pos: [i32; 2], bit_size: f32, relative_pos: (f32, f32)) -> Chunk {
+    pub fn new(pos: [i32; 2]) -> Chunk {
         Chunk {
-            chunk: [0b0000_0000; 8],
-            nodes: Vec::new(),
+            chunk: [0; SIZE],
+            states: Vec::new(),
             active: true,
-            bit_size,
             pos,
-            center: (
-                pos[0] as f32 * bit_size * 8.0,
-                pos[1] as f32 * bit_size * 8.0,
-            ),
-            relative_pos,
         }
     }
-    pub fn from(pos: [i32; 2], chunk: [u8; 8], bit_size: f32, relative_pos: (f32, f32)) -> Chunk {
+    pub fn from(pos: [i32; 2], chunk: [Row; SIZE]) -> Chunk {
         Chunk {
             chunk,
-            nodes: Vec::new(),
+            states: Vec::new(),
             active: true,
-            bit_size,
             pos,
-            center: (
-                pos[0] as f32 * bit_size * 8.0,
-                pos[1] as f32 * bit_size * 8.0,
-            ),
-            relative_pos,
         }
     }
-    pub fn set(&mut self, chunk: [u8; 8]) {
+    pub fn set(&mut self, chunk: [Row; SIZE]) {
         self.chunk = chunk;
     }
     pub fn set_bit(mut self, pos: (u8, u8), val: bool) -> Self {
         if val != self.get_bit_at_point((pos.0 as i8, pos.1 as i8)) {
-            self.chunk[pos.1 as usize] ^= (2 as u8).pow((7 - pos.0) as u32)
+            self.toggle_bit(pos);
         }
         self
     }
     pub fn toggle_bit(&mut self, pos: (u8, u8)) {
-        self.chunk[pos.1 as usize] ^= (2 as u8).pow((7 - pos.0) as u32);
+        self.chunk[pos.1 as usize] ^= 1 << (LAST - pos.0);
+    }
+    // A live cell of colour c is in state c + 1.
+    pub fn state(&self, pos: (u8, u8)) -> u8 {
+        let bit: Row = 1 << (LAST - pos.0);
+        let value = self
+            .states
+            .iter()
+            .enumerate()
+            .filter(|(_, plane)| plane[pos.1 as usize] & bit != 0)
+            .fold(0, |state, (i, _)| state | 1 << i);
+        if self.chunk[pos.1 as usize] & bit != 0 {
+            value + 1
+        } else {
+            value
+        }
+    }
+    // Sets a cell under a Generations rule, where states above 1 are dying.
+    pub fn set_state(&mut self, pos: (u8, u8), state: u8) {
+        let value = if state > 1 { state } else { 0 };
+        self.set_planes(pos, state == 1, value);
+    }
+    // Makes a cell live with the given colour.
+    pub fn set_color(&mut self, pos: (u8, u8), color: u8) {
+        self.set_planes(pos, true, color);
+    }
+    fn set_planes(&mut self, pos: (u8, u8), live: bool, value: u8) {
+        let bit: Row = 1 << (LAST - pos.0);
+        let y = pos.1 as usize;
+        self.chunk[y] &= !bit;
+        if live {
+            self.chunk[y] |= bit;
+        }
+        let bits = 8 - value.leading_zeros() as usize;
+        if self.states.len() < bits {
+            self.states.resize(bits, [0; SIZE]);
+        }
+        for (i, plane) in self.states.iter_mut().enumerate() {
+            plane[y] &= !bit;
+            if value & (1 << i) != 0 {
+                plane[y] |= bit;
+            }
+        }
+    }
+    // Cells in any state other than dead.
+    pub fn occupied(&self, y: usize) -> Row {
+        self.states
+            .iter()
+            .fold(self.chunk[y], |row, plane| row | plane[y])
     }
     pub fn set_active(&mut self, val: bool) {
         self.active = val;
@@ -59,219 +104,414 @@ impl Chunk {
     pub fn toggle_active(&mut self) {
         self.active = !self.active;
     }
-    pub fn top(&self) -> u8 {
+    pub fn is_empty(&self) -> bool {
+        self.chunk.iter().all(|&byte| byte == 0)
+            && self
+                .states
+                .iter()
+                .all(|plane| plane.iter().all(|&row| row == 0))
+    }
+    pub fn top(&self) -> Row {
         self.chunk[0]
     }
-    pub fn bottom(&self) -> u8 {
-        self.chunk[7]
+    pub fn bottom(&self) -> Row {
+        self.chunk[SIZE - 1]
     }
-    pub fn left(&self) -> u8 {
-        let mut out: u8 = 0;
-        for i in 0..8 {
-            if Chunk::get_bit_at(self.chunk[i], 0) {
-                out = out | (2 as u8).pow((7 - i) as u32)
-            }
+    // The left and right columns, packed like a row with y = 0 in the most
+    // significant bit.
+    pub fn left(&self) -> Row {
+        let mut out: Row = 0;
+        for &row in self.chunk.iter() {
+            out = out << 1 | row >> LAST;
         }
         out
     }
-    pub fn right(&self) -> u8 {
-        let mut out: u8 = 0;
-        for i in 0..8 {
-            if Chunk::get_bit_at(self.chunk[i], 7) {
-                out |= (2 as u8).pow((7 - i) as u32)
-            }
+    pub fn right(&self) -> Row {
+        let mut out: Row = 0;
+        for &row in self.chunk.iter() {
+            out = out << 1 | row & 1;
         }
         out
     }
-    pub fn draw(&mut self, window: &mut kiss3d::window::Window, debug: bool) {
-        self.remove_nodes(window);
-        for y in 0..8 {
-            self.draw_byte(self.chunk[y], y, window);
-        }
-        if debug {
-            for x in 0..2 {
-                for y in 0..2 {
-                    let a = (self.bit_size * 4.0 - 0.25) * 2.0 * (x as f32 - 0.5);
-                    let b = (self.bit_size * 4.0 - 0.25) * 2.0 * (y as f32 - 0.5);
-                    window.draw_planar_line(
-                        &Point2::new(
-                            a + self.center.0 + self.relative_pos.0 * self.bit_size,
-                            a + self.center.1 + self.relative_pos.1 * self.bit_size,
-                        ),
-                        &Point2::new(
-                            b + self.center.0 + self.relative_pos.0 * self.bit_size,
-                            -b + self.center.1 + self.relative_pos.1 * self.bit_size,
-                        ),
-                        if self.active {
-                            &Chunk::COLORS.1
-                        } else {
-                            &Chunk::COLORS.0
-                        },
-                    );
+    pub fn iterate(&mut self, edges: &Edges, rule: &Rule) -> u8 {
+        let mut next = Chunk::new(self.pos);
+        let activations = self.step_into(edges, rule, &mut next);
+        *self = next;
+        activations
+    }
+    // Writes the next generation of this chunk into `next`, leaving this one
+    // untouched, and returns the activations.
+    pub fn step_into(&self, edges: &Edges, rule: &Rule, next: &mut Chunk) -> u8 {
+        self.step_with(
+            edges.is_empty(),
+            || self.transitions(edges, rule),
+            rule,
+            next,
+        )
+    }
+    // The same for multi-colour rules, with the colours of the cells around
+    // the chunk in `halo`. Survivors keep their colour and each birth gets
+    // the one `Rule::birth_color` picks from its parents.
+    pub fn step_colored(&self, edges: &Edges, halo: &Halo, rule: &Rule, next: &mut Chunk) -> u8 {
+        let activations = self.step_into(edges, rule, next);
+        if !next.active {
+            return activations;
+        }
+        let mask = rule.neighbourhood.mask();
+        let mut counts = vec![0; rule.colors as usize];
+        next.states.resize(rule.state_bits(), [0; SIZE]);
+        for y in 0..SIZE {
+            let live = self.chunk[y];
+            for (plane, old) in next.states.iter_mut().zip(self.states.iter()) {
+                plane[y] = old[y] & live & next.chunk[y];
+            }
+            let mut born = next.chunk[y] & !live;
+            while born != 0 {
+                let x = LAST as usize - born.trailing_zeros() as usize;
+                counts.iter_mut().for_each(|count| *count = 0);
+                for (i, &(dx, dy)) in NEIGHBOURS.iter().enumerate() {
+                    // the chunk starts at (1, 1) in the halo
+                    let (hx, hy) = ((x as i32 + 1 + dx) as usize, (y as i32 + 1 + dy) as usize);
+                    if mask & (1 << i) != 0 && halo.get(hx, hy) {
+                        let color = halo.state(hx, hy) as usize - 1;
+                        if let Some(count) = counts.get_mut(color) {
+                            *count += 1;
+                        }
+                    }
+                }
+                let color = rule.birth_color(&counts);
+                for (i, plane) in next.states.iter_mut().enumerate() {
+                    if color & (1 << i) != 0 {
+                        plane[y] |= 1 << (LAST as usize - x);
+                    }
                 }
+                born &= born - 1;
             }
         }
+        activations
     }
-    pub fn iterate(&mut self, edges: &Edges) -> u8 {
-        let mut sum: u16 = 0;
-        let mut activations: u8 = 0;
-        if !self.active {
-            for i in 0..8 {
-                sum += self.chunk[i] as u16;
+    // The same under a rule table, looking each cell up in turn. A dead cell
+    // with only dead neighbours stays dead, and states are stored as for
+    // Generations rules.
+    pub fn step_table(&self, halo: &Halo, table: &RuleTable, next: &mut Chunk) -> u8 {
+        next.pos = self.pos;
+        let quiet = halo.ring == 0;
+        next.active = self.active || !(quiet && self.is_empty());
+        if !next.active {
+            next.chunk = self.chunk;
+            next.states.clone_from(&self.states);
+            return 0;
+        }
+        let offsets = table.offsets();
+        let bits = 8 - (table.states - 1).leading_zeros() as usize;
+        next.states.clear();
+        next.states.resize(bits, [0; SIZE]);
+        for y in 0..SIZE {
+            let mut live: Row = 0;
+            for x in 0..SIZE {
+                // the chunk starts at (1, 1) in the halo
+                let (hx, hy) = (x as i32 + 1, y as i32 + 1);
+                let mut cells = [0; 9];
+                cells[0] = halo.state(hx as usize, hy as usize);
+                for (cell, &(dx, dy)) in cells[1..].iter_mut().zip(offsets.iter()) {
+                    *cell = halo.state((hx + dx) as usize, (hy + dy) as usize);
+                }
+                if cells.iter().all(|&cell| cell == 0) {
+                    continue;
+                }
+                let state = table.next(cells);
+                let bit: Row = 1 << (LAST as usize - x);
+                if state == 1 {
+                    live |= bit;
+                } else if state > 1 {
+                    for (i, plane) in next.states.iter_mut().enumerate() {
+                        if state & (1 << i) != 0 {
+                            plane[y] |= bit;
+                        }
+                    }
+                }
             }
+            next.chunk[y] = live;
         }
-        if !((edges.left == 0)
-            && (edges.right == 0)
-            && (edges.top == 0)
-            && (edges.bottom == 0)
-            && (edges.corners == 0)
-            && (sum == 0))
-        {
+        if next.is_empty() && quiet {
+            next.active = false;
+        }
+        let mut occupied = [0; SIZE];
+        for (y, row) in occupied.iter_mut().enumerate() {
+            *row = next.occupied(y);
+        }
+        Chunk::from(self.pos, occupied).activations_within(1)
+    }
+    // The same for rules that reach past the one-cell `Edges`, with the
+    // neighbourhood gathered into a wider halo.
+    pub fn step_range(&self, halo: &Halo, rule: &Rule, next: &mut Chunk) -> u8 {
+        self.step_with(halo.ring == 0, || halo.transitions(rule), rule, next)
+    }
+    // `quiet` is whether there are no live cells around the chunk.
+    fn step_with<F>(&self, quiet: bool, transitions: F, rule: &Rule, next: &mut Chunk) -> u8
+    where
+        F: FnOnce() -> ([Row; SIZE], [Row; SIZE]),
+    {
+        next.pos = self.pos;
+        next.active = self.active || !(quiet && self.is_empty());
+        if !next.active {
+            next.chunk = self.chunk;
+            next.states.clone_from(&self.states);
+            return 0;
+        }
+        let (survives, born) = transitions();
+        if rule.is_generations() {
+            self.next_generations(&survives, &born, rule, next);
+        } else {
+            for (y, next) in next.chunk.iter_mut().enumerate() {
+                let row = self.chunk[y];
+                *next = (row & survives[y]) | (!row & born[y]);
+            }
+            next.states.clear();
+        }
+        if next.is_empty() && quiet {
+            next.active = false;
+        }
+        next.activations_within(rule.radius())
+    }
+    // The original stepping path, which asks `survive` about every cell. Kept
+    // as the reference the bit-parallel `iterate` is checked against.
+    pub fn iterate_by_cell(&mut self, edges: &Edges, rule: &Rule) -> u8 {
+        let mut activations: u8 = 0;
+        if !(edges.is_empty() && self.is_empty()) {
             self.active = true;
         }
         if self.active {
-            let mut new_chunk: [u8; 8] = [0; 8];
+            let mut new_chunk: [Row; SIZE] = [0; SIZE];
             let mut empty = true;
-            for y in 0..8 {
-                let mut byte: u8 = 0b0000_0000;
-                for x in 0..8 {
-                    if self.survive((x, y), &edges) {
-                        byte |= (2 as u8).pow((7 - x) as u32);
+            for (y, row) in new_chunk.iter_mut().enumerate() {
+                let mut byte: Row = 0;
+                for x in 0..SIZE as i8 {
+                    if self.survive((x, y as i8), edges, rule) {
+                        byte |= 1 << (LAST - x as u8);
                         empty = false;
                         activations = Chunk::set_activations(x as u8, y as u8, activations);
                     };
                 }
-                new_chunk[y as usize] = byte;
+                *row = byte;
             }
             self.chunk = new_chunk;
-            if empty {
-                if (edges.left == 0)
-                    && (edges.right == 0)
-                    && (edges.top == 0)
-                    && (edges.bottom == 0)
-                    && (edges.corners == 0)
-                {
-                    self.active = false;
-                }
+            if empty && edges.is_empty() {
+                self.active = false;
             }
         }
-        /* print!("({},{}): ", self.pos[0], self.pos[1]);
-        Chunk::print_byte(activations); */
         activations
     }
-    pub fn survive(&self, point: (i8, i8), edges: &Edges) -> bool {
-        let mut count = 0;
+    // Next generation of the whole chunk under a two-state rule.
+    pub fn next_rows(&self, edges: &Edges, rule: &Rule) -> [Row; SIZE] {
+        let (survives, born) = self.transitions(edges, rule);
+        let mut out = [0; SIZE];
+        for (y, next) in out.iter_mut().enumerate() {
+            let row = self.chunk[y];
+            *next = (row & survives[y]) | (!row & born[y]);
+        }
+        out
+    }
+    // Next generation under a Generations rule. Live cells that don't survive
+    // move to state 2, dying cells count up until they reach `rule.states`
+    // and become dead, and only dead cells can be born. The states are added
+    // to bit-sliced like the neighbour counts.
+    fn next_generations(
+        &self,
+        survives: &[Row; SIZE],
+        born: &[Row; SIZE],
+        rule: &Rule,
+        next: &mut Chunk,
+    ) {
+        let bits = rule.state_bits();
+        next.states.clear();
+        next.states.resize(bits, [0; SIZE]);
+        for y in 0..SIZE {
+            let live = self.chunk[y];
+            let mut value = [0; 8];
+            for (bit, plane) in value.iter_mut().zip(self.states.iter()) {
+                *bit = plane[y];
+            }
+            let dying = value.iter().fold(0, |row, bit| row | bit);
+            // count the dying cells up, then retire those reaching `states`
+            let mut carry = dying;
+            for bit in value[..bits].iter_mut() {
+                let next = *bit & carry;
+                *bit ^= carry;
+                carry = next;
+            }
+            let mut expired: Row = !0;
+            for (i, bit) in value[..bits].iter().enumerate() {
+                expired &= if rule.states & (1 << i) != 0 {
+                    *bit
+                } else {
+                    !*bit
+                };
+            }
+            let died = live & !survives[y];
+            value[1] |= died;
+            for (plane, bit) in next.states.iter_mut().zip(value.iter()) {
+                plane[y] = *bit & !expired;
+            }
+            next.chunk[y] = (live & survives[y]) | (!live & !dying & born[y]);
+        }
+    }
+    // For every cell, whether it would survive if it were alive and whether
+    // it would be born if it were dead. For each row the eight neighbours of
+    // every cell are lined up as whole words (the left and right neighbours
+    // by shifting in the halo cells), then summed in parallel by a
+    // bit-sliced counter: bit n of `sum[i]` is bit i of the neighbour count
+    // of the cell in column n. Under a non-totalistic rule the cells whose
+    // count doesn't settle it are looked up by configuration one at a time.
+    fn transitions(&self, edges: &Edges, rule: &Rule) -> ([Row; SIZE], [Row; SIZE]) {
+        // (left neighbours, cells, right neighbours) of a row and its halo
+        let shifted = |row: Row, left: bool, right: bool| -> (Row, Row, Row) {
+            (
+                row >> 1 | (left as Row) << LAST,
+                row,
+                row << 1 | right as Row,
+            )
+        };
+        let mut rows = [(0, 0, 0); SIZE + 2];
+        rows[0] = shifted(
+            edges.top,
+            Chunk::get_bit_at(edges.corners, 2),
+            Chunk::get_bit_at(edges.corners, 3),
+        );
+        for (y, row) in rows[1..=SIZE].iter_mut().enumerate() {
+            *row = shifted(
+                self.chunk[y],
+                Chunk::get_bit_in_row(edges.left, y as u8),
+                Chunk::get_bit_in_row(edges.right, y as u8),
+            );
+        }
+        rows[SIZE + 1] = shifted(
+            edges.bottom,
+            Chunk::get_bit_at(edges.corners, 0),
+            Chunk::get_bit_at(edges.corners, 1),
+        );
+        let mut survives = [0; SIZE];
+        let mut born = [0; SIZE];
+        let mixed = rule.mixed();
+        // all ones for the neighbours in the rule's neighbourhood
+        let mask = rule.neighbourhood.mask();
+        let lanes: [Row; 8] = std::array::from_fn(|i| if mask & (1 << i) != 0 { !0 } else { 0 });
+        for y in 0..SIZE {
+            let (above, row, below) = (rows[y], rows[y + 1], rows[y + 2]);
+            let neighbours = [
+                above.0, above.1, above.2, row.0, row.2, below.0, below.1, below.2,
+            ];
+            let mut sum: [Row; 4] = [0; 4];
+            for (&n, &lane) in neighbours.iter().zip(lanes.iter()) {
+                let mut carry = n & lane;
+                for bit in sum.iter_mut() {
+                    let next = *bit & carry;
+                    *bit ^= carry;
+                    carry = next;
+                }
+            }
+            let mut undecided: (Row, Row) = (0, 0);
+            for count in 0..9 {
+                let mut matches: Row = !0;
+                for (i, bit) in sum.iter().enumerate() {
+                    matches &= if count & (1 << i) != 0 { *bit } else { !*bit };
+                }
+                if rule.born(count as u8) {
+                    born[y] |= matches;
+                } else if mixed.0 & (1 << count) != 0 {
+                    undecided.0 |= matches & !row.1;
+                }
+                if rule.survives(count as u8) {
+                    survives[y] |= matches;
+                } else if mixed.1 & (1 << count) != 0 {
+                    undecided.1 |= matches & row.1;
+                }
+            }
+            let config = |column: u32| {
+                neighbours.iter().enumerate().fold(0u8, |config, (i, n)| {
+                    config | ((n >> column & 1) as u8) << i
+                })
+            };
+            let mut cells = undecided.0 | undecided.1;
+            while cells != 0 {
+                let column = cells.trailing_zeros();
+                let bit: Row = 1 << column;
+                if undecided.0 & bit != 0 && rule.born_in(config(column)) {
+                    born[y] |= bit;
+                }
+                if undecided.1 & bit != 0 && rule.survives_in(config(column)) {
+                    survives[y] |= bit;
+                }
+                cells &= cells - 1;
+            }
+        }
+        (survives, born)
+    }
+    pub fn survive(&self, point: (i8, i8), edges: &Edges, rule: &Rule) -> bool {
+        let mut config: u8 = 0;
         for x in -1..2 {
             for y in -1..2 {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+                // the neighbour's bit in the configuration, NW to SE
+                let n = (y + 1) * 3 + x + 1;
+                let neighbour: u8 = 1 << if n > 4 { n - 1 } else { n };
                 if point.0 == 0 && x == -1 {
                     // left edge
-                    if point.1 + y >= 0 && point.1 + y < 8 {
-                        if Chunk::get_bit_at(edges.left, (point.1 + y) as u8) {
-                            count += 1;
-                        }
-                    } else {
-                        // corner case
-                        if point.1 + y == -1 {
-                            // top corner
-                            if Chunk::get_bit_at(edges.corners, 2) {
-                                count += 1;
-                            }
+                    if point.1 + y >= 0 && point.1 + y <= LAST as i8 {
+                        if Chunk::get_bit_in_row(edges.left, (point.1 + y) as u8) {
+                            config |= neighbour;
                         }
-                        if point.1 + y == 8 {
-                            // bottom corner
-                            if Chunk::get_bit_at(edges.corners, 0) {
-                                count += 1;
-                            }
+                    } else if point.1 + y == -1 {
+                        // top corner
+                        if Chunk::get_bit_at(edges.corners, 2) {
+                            config |= neighbour;
                         }
+                    } else if Chunk::get_bit_at(edges.corners, 0) {
+                        // bottom corner
+                        config |= neighbour;
                     }
                     continue;
                 }
-                if point.0 == 7 && x == 1 {
+                if point.0 == LAST as i8 && x == 1 {
                     // right edge
-                    if point.1 + y >= 0 && point.1 + y < 8 {
-                        if Chunk::get_bit_at(edges.right, (point.1 + y) as u8) {
-                            count += 1;
+                    if point.1 + y >= 0 && point.1 + y <= LAST as i8 {
+                        if Chunk::get_bit_in_row(edges.right, (point.1 + y) as u8) {
+                            config |= neighbour;
                         }
-                    } else {
-                        // corner case
-                        if point.1 + y == -1 {
-                            // top corner
-                            if Chunk::get_bit_at(edges.corners, 3) {
-                                count += 1;
-                            }
-                        }
-                        if point.1 + y == 8 {
-                            // bottom corner
-                            if Chunk::get_bit_at(edges.corners, 1) {
-                                count += 1;
-                            }
+                    } else if point.1 + y == -1 {
+                        // top corner
+                        if Chunk::get_bit_at(edges.corners, 3) {
+                            config |= neighbour;
                         }
+                    } else if Chunk::get_bit_at(edges.corners, 1) {
+                        // bottom corner
+                        config |= neighbour;
                     }
                     continue;
                 }
                 if point.1 == 0 && y == -1 {
                     // top edge
-                    if point.0 + x >= 0 && point.0 + x < 8 {
-                        if Chunk::get_bit_at(edges.top, (point.0 + x) as u8) {
-                            count += 1;
-                        }
-                    } else {
+                    if Chunk::get_bit_in_row(edges.top, (point.0 + x) as u8) {
+                        config |= neighbour;
                     }
                     continue;
                 }
-                if point.1 == 7 && y == 1 {
+                if point.1 == LAST as i8 && y == 1 {
                     // bottom edge
-                    if point.0 + x >= 0 && point.0 + x < 8 {
-                        if Chunk::get_bit_at(edges.bottom, (point.0 + x) as u8) {
-                            count += 1;
-                        }
-                    } else {
+                    if Chunk::get_bit_in_row(edges.bottom, (point.0 + x) as u8) {
+                        config |= neighbour;
                     }
                     continue;
                 }
-                if point.0 == 0 && point.1 == 1 {
-                    let new_point = (point.0 + x, point.1 + y);
-                    let b = self.get_bit_at_point(new_point);
-                    // println!("({},{}): {}", x, y, b);
-                }
                 let new_point = (point.0 + x, point.1 + y);
                 if self.get_bit_at_point(new_point) {
-                    count += 1;
+                    config |= neighbour;
                 }
             }
         }
-        if self.get_bit_at_point(point) {
-            count == 3 || count == 4
-        } else {
-            count == 3
-        }
-    }
-    pub fn draw_byte(&mut self, byte: u8, y: usize, window: &mut kiss3d::window::Window) {
-        for x in 0..8 {
-            if Chunk::get_bit_at(byte, x) {
-                let mut c = window.add_rectangle(self.bit_size, self.bit_size);
-                // c.set_color(0.0, 0.0, 0.0);
-                c.append_translation(&Translation2::new(
-                    (x as f32 - 3.5) * self.bit_size
-                        + self.center.0
-                        + self.relative_pos.0 * self.bit_size,
-                    ((7 - y) as f32 - 3.5) * self.bit_size
-                        + self.center.1
-                        + self.relative_pos.1 * self.bit_size,
-                ));
-                self.nodes.push(c);
-            };
-        }
-    }
-    pub fn update_pos(&mut self, pos: (f32, f32)) {
-        self.relative_pos = (pos.0, pos.1);
-    }
-    pub fn update_zoom(&mut self, zoom: f32) {
-        self.bit_size = zoom;
-        self.center = (
-            self.pos[0] as f32 * zoom * 8.0,
-            self.pos[1] as f32 * zoom * 8.0,
-        );
+        rule.next_cell(self.get_bit_at_point(point), config)
     }
     pub fn get_bit_at(input: u8, n: u8) -> bool {
         let n = 7 - n;
@@ -281,29 +521,23 @@ impl Chunk {
             panic!("{} is out of bounds", n);
         }
     }
+    pub fn get_bit_in_row(row: Row, n: u8) -> bool {
+        if n <= LAST {
+            row & (1 << (LAST - n)) != 0
+        } else {
+            panic!("{} is out of bounds", n);
+        }
+    }
     pub fn get_bit_at_point(&self, point: (i8, i8)) -> bool {
-        Chunk::get_bit_at(self.chunk[point.1 as usize], point.0 as u8)
+        Chunk::get_bit_in_row(self.chunk[point.1 as usize], point.0 as u8)
     }
-    pub fn get_byte_at(&self, n: usize) -> u8 {
+    pub fn get_byte_at(&self, n: usize) -> Row {
         self.chunk[n]
     }
-    pub fn remove_nodes(&mut self, window: &mut kiss3d::window::Window) {
-        for i in 0..self.nodes.len() {
-            window.remove_planar_node(&mut self.nodes[i]);
-        }
-        self.nodes.clear();
-    }
     pub fn print(&self) {
-        for i in 0..8 {
-            for j in 0..8 {
-                print!(
-                    "{}",
-                    if Chunk::get_bit_at(self.chunk[i], j) {
-                        1
-                    } else {
-                        0
-                    }
-                );
+        for row in self.chunk.iter() {
+            for j in 0..SIZE as u8 {
+                print!("{}", if Chunk::get_bit_in_row(*row, j) { 1 } else { 0 });
             }
             println!();
         }
@@ -314,6 +548,63 @@ impl Chunk {
         }
         println!();
     }
+    // Neighbours that need to exist because a live cell touches their border.
+    pub fn activations(&self) -> u8 {
+        self.activations_within(1)
+    }
+    // The same for rules reaching `margin` cells, up to SIZE: neighbours
+    // within that many cells of a live cell.
+    pub fn activations_within(&self, margin: usize) -> u8 {
+        let first: Row = !0 << (SIZE - margin);
+        let last: Row = !0 >> (SIZE - margin);
+        let any = |rows: &[Row]| rows.iter().fold(0, |out, &row| out | row);
+        let top = any(&self.chunk[..margin]);
+        let bottom = any(&self.chunk[SIZE - margin..]);
+        let all = any(&self.chunk);
+        let mut v: u8 = 0;
+        for (live, bits) in [
+            (top & first, 0b1000_0000),
+            (top, 0b0100_0000),
+            (top & last, 0b0010_0000),
+            (all & first, 0b0001_0000),
+            (all & last, 0b0000_1000),
+            (bottom & first, 0b0000_0100),
+            (bottom, 0b0000_0010),
+            (bottom & last, 0b0000_0001),
+        ]
+        .iter()
+        {
+            if *live != 0 {
+                v |= bits;
+            }
+        }
+        v
+    }
+    // The neighbours a live cell at (x, y) reaches with rules of radius
+    // `margin`.
+    pub fn activations_near(x: u8, y: u8, margin: usize) -> u8 {
+        let margin = margin.min(SIZE) as u8;
+        let (left, right) = (x < margin, x > LAST - margin);
+        let (top, bottom) = (y < margin, y > LAST - margin);
+        let mut v: u8 = 0;
+        for (near, bits) in [
+            (top && left, 0b1000_0000),
+            (top, 0b0100_0000),
+            (top && right, 0b0010_0000),
+            (left, 0b0001_0000),
+            (right, 0b0000_1000),
+            (bottom && left, 0b0000_0100),
+            (bottom, 0b0000_0010),
+            (bottom && right, 0b0000_0001),
+        ]
+        .iter()
+        {
+            if *near {
+                v |= bits;
+            }
+        }
+        v
+    }
     pub fn set_activations(x: u8, y: u8, activations: u8) -> u8 {
         let mut v: u8 = activations;
         if x == 0 {
@@ -321,109 +612,158 @@ impl Chunk {
             if y == 0 {
                 v |= 0b1000_0000;
             }
-            if y == 7 {
+            if y == LAST {
                 v |= 0b0000_0100;
             }
         }
-        if x == 7 {
+        if x == LAST {
             v |= 0b0000_1000;
             if y == 0 {
                 v |= 0b0010_0000;
             }
-            if y == 7 {
+            if y == LAST {
                 v |= 0b0000_0001;
             }
         }
         if y == 0 {
             v |= 0b0100_0000;
         }
-        if y == 7 {
+        if y == LAST {
             v |= 0b0000_0010;
         }
         v
     }
 }
 
+// (dx, dy) of each neighbour in configuration order, NW to SE.
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Edges {
-    pub left: u8,
-    pub right: u8,
-    pub top: u8,
-    pub bottom: u8,
+    pub left: Row,
+    pub right: Row,
+    pub top: Row,
+    pub bottom: Row,
     pub corners: u8,
 }
+impl Edges {
+    pub fn is_empty(&self) -> bool {
+        self.left == 0 && self.right == 0 && self.top == 0 && self.bottom == 0 && self.corners == 0
+    }
+}
 
-pub struct HoverChunk {
-    nodes: Vec<kiss3d::scene::PlanarSceneNode>,
-    active: bool,
-    pub bit_size: f32,
-    pub pos: [i32; 2],
-    center: (f32, f32),
-    relative_pos: (f32, f32),
+// The live cells within `radius` of a chunk, for rules that reach past the
+// one-cell `Edges`: a square of side SIZE + 2 * radius, row by row, with the
+// chunk in the middle. `ring` counts the cells outside the chunk that aren't
+// dead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Halo {
+    pub radius: usize,
+    pub cells: Vec<bool>,
+    pub ring: usize,
+    // The state of every cell under a rule table or a multi-colour rule, and
+    // empty otherwise.
+    pub states: Vec<u8>,
 }
-impl HoverChunk {
-    pub fn new(pos: [i32; 2], bit_size: f32, relative_pos: (f32, f32)) -> HoverChunk {
-        HoverChunk {
-            nodes: Vec::new(),
-            active: false,
-            bit_size,
-            pos,
-            center: (
-                pos[0] as f32 * bit_size * 8.0,
-                pos[1] as f32 * bit_size * 8.0,
-            ),
-            relative_pos,
+impl Halo {
+    pub fn new(radius: usize) -> Halo {
+        let side = SIZE + 2 * radius;
+        Halo {
+            radius,
+            cells: vec![false; side * side],
+            ring: 0,
+            states: Vec::new(),
         }
     }
-    pub fn draw(&mut self, window: &mut kiss3d::window::Window) {
-        self.remove_nodes(window);
-        if self.active {
-            // println!("drawing at pt: ({},{})", self.center.0, self.center.1);
-            for x in 0..2 {
-                for y in 0..2 {
-                    let a = (self.bit_size * 4.0 - 0.25) * 2.0 * (x as f32 - 0.5);
-                    let b = (self.bit_size * 4.0 - 0.25) * 2.0 * (y as f32 - 0.5);
-                    window.draw_planar_line(
-                        &Point2::new(
-                            a + self.center.0 + self.relative_pos.0 * self.bit_size,
-                            a + self.center.1 + self.relative_pos.1 * self.bit_size,
-                        ),
-                        &Point2::new(
-                            b + self.center.0 + self.relative_pos.0 * self.bit_size,
-                            -b + self.center.1 + self.relative_pos.1 * self.bit_size,
-                        ),
-                        &Point3::new(0.0, 0.25, 0.0),
-                    );
-                }
-            }
-        }
+    pub fn side(&self) -> usize {
+        SIZE + 2 * self.radius
     }
-    pub fn remove_nodes(&mut self, window: &mut kiss3d::window::Window) {
-        for i in 0..self.nodes.len() {
-            window.remove_planar_node(&mut self.nodes[i]);
+    // (x, y) from the top left of the halo, so the chunk starts at
+    // (radius, radius).
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.side() + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize) {
+        let side = self.side();
+        if self.cells[y * side + x] {
+            return;
+        }
+        if self.outside(x, y) && self.state(x, y) == 0 {
+            self.ring += 1;
         }
-        self.nodes.clear();
+        self.cells[y * side + x] = true;
     }
-    pub fn update_relative_pos(&mut self, relative_pos: (f32, f32)) {
-        self.active = true;
-        self.relative_pos = relative_pos;
+    pub fn state(&self, x: usize, y: usize) -> u8 {
+        self.states.get(y * self.side() + x).copied().unwrap_or(0)
     }
-    pub fn update_pos(&mut self, pos: [i32; 2]) {
-        self.active = true;
-        self.pos = pos;
-        self.center = (
-            pos[0] as f32 * self.bit_size * 8.0,
-            pos[1] as f32 * self.bit_size * 8.0,
-        );
+    pub fn set_state(&mut self, x: usize, y: usize, state: u8) {
+        let side = self.side();
+        if self.states.is_empty() {
+            self.states = vec![0; side * side];
+        }
+        if self.outside(x, y) && !self.get(x, y) && self.state(x, y) == 0 && state != 0 {
+            self.ring += 1;
+        }
+        self.states[y * side + x] = state;
     }
-    pub fn update_zoom(&mut self, zoom: f32) {
-        self.active = true;
-        self.bit_size = zoom;
-        self.center = (
-            self.pos[0] as f32 * zoom * 8.0,
-            self.pos[1] as f32 * zoom * 8.0,
-        );
+    fn outside(&self, x: usize, y: usize) -> bool {
+        let inside = self.radius..self.radius + SIZE;
+        !(inside.contains(&x) && inside.contains(&y))
     }
-    pub fn set_inactive(&mut self) {
-        self.active = false
+    // Survival and birth masks for the chunk under a Larger than Life rule,
+    // from running sums along each row: a square neighbourhood adds up the
+    // sums of 2r + 1 rows, a diamond narrows them with distance.
+    pub fn transitions(&self, rule: &Rule) -> ([Row; SIZE], [Row; SIZE]) {
+        let range = match &rule.range {
+            Some(range) => range,
+            None => return ([0; SIZE], [0; SIZE]),
+        };
+        let (side, r) = (self.side(), self.radius);
+        // sums[y][x] is the number of live cells left of column x in row y
+        let mut sums = vec![0u16; side * (side + 1)];
+        for y in 0..side {
+            for x in 0..side {
+                sums[y * (side + 1) + x + 1] = sums[y * (side + 1) + x] + self.get(x, y) as u16;
+            }
+        }
+        let row_sum = |y: usize, from: usize, to: usize| {
+            sums[y * (side + 1) + to + 1] - sums[y * (side + 1) + from]
+        };
+        let mut survives = [0; SIZE];
+        let mut born = [0; SIZE];
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (cx, cy) = (x + r, y + r);
+                let mut count = 0;
+                for dy in 0..=2 * r {
+                    let reach = match rule.neighbourhood {
+                        Neighbourhood::VonNeumann => r - (dy as isize - r as isize).unsigned_abs(),
+                        _ => r,
+                    };
+                    count += row_sum(cy + dy - r, cx - reach, cx + reach);
+                }
+                let alive = self.get(cx, cy);
+                if !range.middle {
+                    count -= alive as u16;
+                }
+                let bit: Row = 1 << (LAST as usize - x);
+                if range.survives(count) {
+                    survives[y] |= bit;
+                }
+                if range.born(count) {
+                    born[y] |= bit;
+                }
+            }
+        }
+        (survives, born)
     }
 }
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::universe::Universe;
+
+// What pressing the mouse does. Every tool but `Toggle` paints when the
+// stroke starts on a dead cell and erases when it starts on a live one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    // Toggles the cell clicked, as a plain click always has.
+    #[default]
+    Toggle,
+    // Paints every cell the cursor passes over while dragging.
+    Pencil,
+    // A straight line from where the drag starts to where it ends.
+    Line,
+    Rectangle,
+    FilledRectangle,
+    // Fills the region of cells like the one clicked, if it is enclosed.
+    Fill,
+}
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Tool::Toggle => "toggle",
+            Tool::Pencil => "pencil",
+            Tool::Line => "line",
+            Tool::Rectangle => "rectangle",
+            Tool::FilledRectangle => "filled rectangle",
+            Tool::Fill => "fill",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// The largest region `fill` will fill.
+pub const MAX_FILL: usize = 1 << 20;
+
+// The cells on a line from `a` to `b`, both included, by Bresenham's
+// algorithm.
+pub fn line(a: (i32, i32), b: (i32, i32)) -> Vec<(i32, i32)> {
+    let (dx, dy) = ((b.0 - a.0).abs(), -(b.1 - a.1).abs());
+    let (sx, sy) = ((b.0 - a.0).signum(), (b.1 - a.1).signum());
+    let mut cells = Vec::new();
+    let (mut x, mut y) = a;
+    let mut error = dx + dy;
+    loop {
+        cells.push((x, y));
+        if (x, y) == b {
+            return cells;
+        }
+        let twice = 2 * error;
+        if twice >= dy {
+            error += dy;
+            x += sx;
+        }
+        if twice <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+// The cells of the rectangle with `a` and `b` at opposite corners, either
+// all of them or just its edges.
+pub fn rectangle(a: (i32, i32), b: (i32, i32), filled: bool) -> Vec<(i32, i32)> {
+    let (left, right) = (a.0.min(b.0), a.0.max(b.0));
+    let (top, bottom) = (a.1.min(b.1), a.1.max(b.1));
+    let mut cells = Vec::new();
+    for y in top..=bottom {
+        for x in left..=right {
+            if filled || x == left || x == right || y == top || y == bottom {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+// The cells orthogonally connected to `start` that are in the same state.
+// In a bounded world the region follows joined edges round to the other side
+// and stops at walls. The region has to be enclosed: None when, on the
+// infinite plane, it reaches past the pattern's bounding box, or when it is
+// larger than MAX_FILL.
+pub fn fill(universe: &Universe, start: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+    let topology = universe.topology;
+    let bounds = if topology.is_bounded() {
+        None
+    } else {
+        Some(universe.pattern().bounding_box()?)
+    };
+    let start = topology.wrap_cell(start.0, start.1)?;
+    let state = universe.get_state(start.0, start.1);
+    let mut seen = HashSet::new();
+    let mut todo = vec![start];
+    seen.insert(start);
+    while let Some((x, y)) = todo.pop() {
+        if let Some((left, top, right, bottom)) = bounds {
+            if !(left..=right).contains(&x) || !(top..=bottom).contains(&y) {
+                return None;
+            }
+        }
+        if seen.len() > MAX_FILL {
+            return None;
+        }
+        for (x, y) in [(x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)] {
+            let next = match topology.wrap_cell(x, y) {
+                Some(next) => next,
+                None => continue,
+            };
+            if universe.get_state(next.0, next.1) == state && seen.insert(next) {
+                todo.push(next);
+            }
+        }
+    }
+    let mut cells: Vec<(i32, i32)> = seen.into_iter().collect();
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    Some(cells)
+}
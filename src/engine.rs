@@ -0,0 +1,22 @@
+// Queries shared by every simulation backend, so tools can inspect and
+// drive a pattern without caring which engine holds it.
+pub trait Engine {
+    fn get_cell(&self, x: i32, y: i32) -> bool;
+    fn set_cell(&mut self, x: i32, y: i32, val: bool);
+    // Live cells sorted row by row.
+    fn cells(&self) -> Vec<(i32, i32)>;
+    fn population(&self) -> u64;
+    // (min_x, min_y, max_x, max_y) of the live cells.
+    fn bounding_box(&self) -> Option<(i32, i32, i32, i32)>;
+    fn generation(&self) -> u64;
+    fn step(&mut self, generations: u64);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EngineKind {
+    // The chunk map in `Universe`, stepped one generation at a time.
+    #[default]
+    Chunks,
+    // Memoised quadtrees from `hashlife`, for large jumps.
+    HashLife,
+}
@@ -0,0 +1,159 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::life106::Life106Error;
+use crate::plaintext::PlaintextError;
+use crate::rle::RleError;
+use crate::save::SaveError;
+use crate::table::TableError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    Cgl,
+    Rle,
+    Plaintext,
+    Life106,
+    // Golly rule tables, which can be opened but not saved.
+    Table,
+}
+impl FileFormat {
+    // Filter string for native file dialogs.
+    pub const FILTER: &'static str = "cgl;rle;cells;lif,life;rule";
+    pub fn from_path(path: &Path) -> Option<FileFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "cgl" => Some(FileFormat::Cgl),
+            "rle" => Some(FileFormat::Rle),
+            "cells" => Some(FileFormat::Plaintext),
+            "lif" | "life" => Some(FileFormat::Life106),
+            "rule" => Some(FileFormat::Table),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    UnknownFormat(PathBuf),
+    Save(SaveError),
+    Rle(RleError),
+    Plaintext(PlaintextError),
+    Life106(Life106Error),
+    Table(TableError),
+}
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "{}", e),
+            FileError::UnknownFormat(path) => write!(
+                f,
+                "don't know how to handle {} (expected .cgl, .rle, .cells or .lif, or .rule to open)",
+                path.display()
+            ),
+            FileError::Save(e) => write!(f, "{}", e),
+            FileError::Rle(e) => write!(f, "{}", e),
+            FileError::Plaintext(e) => write!(f, "{}", e),
+            FileError::Life106(e) => write!(f, "{}", e),
+            FileError::Table(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for FileError {}
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> FileError {
+        FileError::Io(e)
+    }
+}
+impl From<SaveError> for FileError {
+    fn from(e: SaveError) -> FileError {
+        match e {
+            SaveError::Io(e) => FileError::Io(e),
+            e => FileError::Save(e),
+        }
+    }
+}
+impl From<RleError> for FileError {
+    fn from(e: RleError) -> FileError {
+        FileError::Rle(e)
+    }
+}
+impl From<PlaintextError> for FileError {
+    fn from(e: PlaintextError) -> FileError {
+        FileError::Plaintext(e)
+    }
+}
+impl From<Life106Error> for FileError {
+    fn from(e: Life106Error) -> FileError {
+        FileError::Life106(e)
+    }
+}
+impl From<TableError> for FileError {
+    fn from(e: TableError) -> FileError {
+        FileError::Table(e)
+    }
+}
+
+// Most recently used files, newest first, persisted as one path per line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecentFiles {
+    pub paths: Vec<PathBuf>,
+    file: Option<PathBuf>,
+}
+impl RecentFiles {
+    pub const LIMIT: usize = 9;
+    pub fn new() -> RecentFiles {
+        RecentFiles::default()
+    }
+    // Loads the list kept in the user's home directory (or the working
+    // directory when there is none).
+    pub fn load_default() -> RecentFiles {
+        let dir = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        RecentFiles::load(dir.join(".life-rust-recent"))
+    }
+    pub fn load(file: PathBuf) -> RecentFiles {
+        let paths = fs::read_to_string(&file)
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .take(RecentFiles::LIMIT)
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        RecentFiles {
+            paths,
+            file: Some(file),
+        }
+    }
+    pub fn push(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(RecentFiles::LIMIT);
+        if let Err(e) = self.store() {
+            println!("could not update recent files: {}", e);
+        }
+    }
+    pub fn get(&self, n: usize) -> Option<&Path> {
+        self.paths.get(n).map(|p| p.as_path())
+    }
+    fn store(&self) -> io::Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let mut text = String::new();
+        for path in self.paths.iter() {
+            text.push_str(&path.to_string_lossy());
+            text.push('\n');
+        }
+        fs::write(file, text)
+    }
+}
@@ -1,335 +1,800 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::chunk::Chunk;
-use crate::chunk::Edges;
-use crate::chunk::HoverChunk;
+use crate::chunk::Row;
+use crate::chunk::SIZE;
+use crate::draw;
+use crate::draw::Tool;
+use crate::engine::Engine;
+use crate::engine::EngineKind;
+use crate::files::FileError;
+use crate::files::FileFormat;
+use crate::hashlife::HashLife;
+use crate::history::{CellEdit, Edit, History, Rewind};
+use crate::library;
+use crate::life106;
+use crate::pattern::{Pattern, Transform};
+use crate::plaintext;
+use crate::rle;
+use crate::rle::RleError;
+use crate::rule::Rule;
+use crate::save;
+use crate::save::SaveData;
+use crate::selection::Selection;
+use crate::soup;
+use crate::soup::Symmetry;
+use crate::table::RuleTable;
+use crate::topology::Topology;
+use crate::universe::Universe;
 
-use kiss3d::event::{Modifiers, MouseButton};
-use kiss3d::nalgebra::Point2;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use std::fs::File;
-use std::io::prelude::*;
+// A drag in progress with one of the drawing tools, from `start` to `last`.
+struct Stroke {
+    // The tool it was started with, which it keeps if `Game::tool` changes.
+    tool: Tool,
+    start: (i32, i32),
+    last: (i32, i32),
+    state: u8,
+    // What the pencil has changed so far, undone together.
+    edits: Vec<CellEdit>,
+}
 
+// A universe plus the camera state used to map screen positions onto cells.
+// Rendering lives in `view`, so a `Game` can be driven without a window.
 pub struct Game {
-    pub map: HashMap<[i32; 2], Chunk>,
-    bit_size: f32,
-    relative_pos: (f32, f32),
-    debug: bool,
-    hover_chunk: HoverChunk,
+    pub universe: Universe,
+    pub bit_size: f32,
+    pub relative_pos: (f32, f32),
+    pub debug: bool,
+    pub hover_chunk: Option<[i32; 2]>,
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub path: Option<PathBuf>,
+    pub engine: EngineKind,
+    // `jump` advances 2^jump_exponent generations at once.
+    pub jump_exponent: u8,
+    // The state clicks paint under a multi-colour rule, 1 for the first colour.
+    pub color: u8,
+    // Rule tables opened so far, which patterns can name as their rule.
+    pub tables: Vec<Arc<RuleTable>>,
+    // Edits that Ctrl+Z and Ctrl+Y undo and redo.
+    pub history: History,
+    // Recent generations that can be gone back to.
+    pub rewind: Rewind,
+    // What pressing the mouse does.
+    pub tool: Tool,
+    stroke: Option<Stroke>,
+    // The cell under the mouse.
+    pub cursor: (i32, i32),
+    pub selection: Option<Selection>,
+    // RLE text last copied or cut.
+    pub clipboard: Option<String>,
+    // A pattern being pasted, which follows the cursor until a click places
+    // it with its top left corner there.
+    pub pasting: Option<Pattern>,
+    // The entry of the built-in library last picked by `browse`.
+    pub browsing: Option<usize>,
+    // Kept between steps so HashLife's memoised results can be reused. The
+    // tree holds the cells of universe revision `hashlife_revision`, or while
+    // `hashlife_ahead` is set it runs ahead of `universe`, which `sync`
+    // brings up to date.
+    hashlife: Option<HashLife>,
+    hashlife_revision: Option<u64>,
+    hashlife_ahead: bool,
 }
 impl Game {
     pub fn new(debug: bool) -> Game {
-        let mut map: HashMap<[i32; 2], Chunk> = HashMap::new();
-        map.insert([0, 0], Chunk::new([0, 0], 10.0, (0.0, 0.0)));
-        Game {
-            map,
-            bit_size: 10.0,
-            relative_pos: (0.0, 0.0),
-            debug,
-            hover_chunk: HoverChunk::new([0, 0], 10.0, (0.0, 0.0)),
-        }
+        Game::from_universe(Universe::new(), debug)
     }
-    pub fn from(chunk: [u8; 8], debug: bool) -> Game {
-        let mut map: HashMap<[i32; 2], Chunk> = HashMap::new();
-        map.insert([0, 0], Chunk::from([0, 0], chunk, 10.0, (0.0, 0.0)));
-        Game {
-            map,
-            bit_size: 10.0,
-            relative_pos: (0.0, 0.0),
-            debug,
-            hover_chunk: HoverChunk::new([0, 0], 10.0, (0.0, 0.0)),
-        }
+    pub fn from(chunk: [Row; SIZE], debug: bool) -> Game {
+        Game::from_universe(Universe::from(chunk), debug)
     }
     pub fn from_chunk(chunk: Chunk, debug: bool) -> Game {
-        let mut map: HashMap<[i32; 2], Chunk> = HashMap::new();
-        map.insert([0, 0], chunk);
+        let mut universe = Universe::new();
+        universe.insert_chunk([0, 0], chunk);
+        Game::from_universe(universe, debug)
+    }
+    pub fn from_universe(universe: Universe, debug: bool) -> Game {
         Game {
-            map,
+            universe,
             bit_size: 10.0,
             relative_pos: (0.0, 0.0),
             debug,
-            hover_chunk: HoverChunk::new([0, 0], 10.0, (0.0, 0.0)),
+            hover_chunk: None,
+            name: None,
+            comments: Vec::new(),
+            path: None,
+            engine: EngineKind::default(),
+            jump_exponent: 10,
+            color: 1,
+            tables: Vec::new(),
+            history: History::default(),
+            rewind: Rewind::default(),
+            tool: Tool::default(),
+            stroke: None,
+            cursor: (0, 0),
+            selection: None,
+            clipboard: None,
+            pasting: None,
+            browsing: None,
+            hashlife: None,
+            hashlife_revision: None,
+            hashlife_ahead: false,
         }
     }
-    pub fn click(&mut self, sel_pos: Point2<f32>, button: MouseButton, modif: Modifiers) {
+    // The screen origin is the centre of chunk [0, 0].
+    const HALF_CHUNK: f32 = SIZE as f32 / 2.0;
+    pub fn cell_at(&self, sel_pos: (f32, f32)) -> (i32, i32) {
         let pos = (
-            sel_pos.coords[0] - self.relative_pos.0 * self.bit_size,
-            sel_pos.coords[1] - self.relative_pos.1 * self.bit_size,
+            sel_pos.0 / self.bit_size - self.relative_pos.0,
+            sel_pos.1 / self.bit_size - self.relative_pos.1,
         );
-        let chunk: [i32; 2] = [
-            (pos.0 / (self.bit_size * 8.0) + 0.5).floor() as i32,
-            (pos.1 / (self.bit_size * 8.0) + 0.5).floor() as i32,
-        ];
-        let bit: [u8; 2] = [
-            (pos.0 / self.bit_size - chunk[0] as f32 * 8.0 + 4.0).floor() as u8,
-            (-pos.1 / self.bit_size + chunk[1] as f32 * 8.0 + 4.0).floor() as u8,
-        ];
-        println!("{:?} {:?}", pos, chunk);
-        match self.map.get_mut(&chunk) {
-            Some(i) => {
-                i.toggle_bit((bit[0], bit[1]));
+        (
+            (pos.0 + Game::HALF_CHUNK).floor() as i32,
+            (-pos.1 + Game::HALF_CHUNK).floor() as i32,
+        )
+    }
+    pub fn cell_center(&self, cell: (i32, i32)) -> (f32, f32) {
+        (
+            (cell.0 as f32 + 0.5 - Game::HALF_CHUNK + self.relative_pos.0) * self.bit_size,
+            (Game::HALF_CHUNK - 0.5 - cell.1 as f32 + self.relative_pos.1) * self.bit_size,
+        )
+    }
+    pub fn chunk_center(&self, pos: [i32; 2]) -> (f32, f32) {
+        (
+            (pos[0] as f32 * SIZE as f32 + self.relative_pos.0) * self.bit_size,
+            (pos[1] as f32 * SIZE as f32 + self.relative_pos.1) * self.bit_size,
+        )
+    }
+    // Toggles the cell, or under a multi-colour rule paints it in `color`,
+    // clearing it when it already has that colour. While pasting, places the
+    // pattern instead.
+    pub fn click(&mut self, sel_pos: (f32, f32)) {
+        self.sync();
+        let (x, y) = self.cell_at(sel_pos);
+        if let Some(mut pattern) = self.pasting.take() {
+            pattern.translate((x, y));
+            self.set_states(&pattern.all_states());
+            return;
+        }
+        let before = self.universe.get_state(x, y);
+        let colors = self.universe.rule.colors;
+        let state = if colors > 1 {
+            let color = self.color.clamp(1, colors);
+            if before == color {
+                0
+            } else {
+                color
             }
-            None => {
-                let mut newchunk: Chunk = Chunk::new(chunk, self.bit_size, self.relative_pos);
-                newchunk.toggle_bit((bit[0], bit[1]));
-                self.map.insert(
-                    chunk,
-                    newchunk,
-                );
+        } else if before == 0 {
+            1
+        } else {
+            0
+        };
+        self.set_states(&[(x, y, state)]);
+    }
+    // Sets each cell to its state as a single edit for undoing.
+    pub fn set_states(&mut self, cells: &[(i32, i32, u8)]) {
+        let edits = self.apply(cells);
+        self.history.push(Edit::Cells(edits));
+    }
+    fn apply(&mut self, cells: &[(i32, i32, u8)]) -> Vec<CellEdit> {
+        self.sync();
+        let mut edits = Vec::new();
+        for &(x, y, state) in cells {
+            let before = self.universe.get_state(x, y);
+            self.universe.set_state(x, y, state);
+            let after = self.universe.get_state(x, y);
+            if after != before {
+                edits.push(CellEdit {
+                    x,
+                    y,
+                    before,
+                    after,
+                });
             }
         }
+        edits
     }
-    pub fn hover(&mut self, hover_pos: Point2<f32>) {
-        let pos = (
-            hover_pos.coords[0] - self.relative_pos.0 * self.bit_size,
-            hover_pos.coords[1] - self.relative_pos.1 * self.bit_size,
-        );
-        let chunk: [i32; 2] = [
-            (pos.0 / (self.bit_size * 8.0) + 0.5).floor() as i32,
-            (pos.1 / (self.bit_size * 8.0) + 0.5).floor() as i32,
-        ];
-        // println!("{:?} {:?}", pos, chunk);
-        match self.map.get(&chunk) {
-            Some(_) => self.hover_chunk.set_inactive(),
-            None => self.hover_chunk.update_pos(chunk),
+    // Starts a stroke with the current tool, or with `Tool::Toggle` (or
+    // while pasting) just clicks. A stroke paints in `color` if it starts on
+    // a dead cell and erases otherwise. Returns false when the press does
+    // nothing, which is when the region to fill isn't enclosed.
+    pub fn press(&mut self, sel_pos: (f32, f32)) -> bool {
+        if self.tool == Tool::Toggle || self.pasting.is_some() {
+            self.click(sel_pos);
+            return true;
         }
-    }
-    pub fn pos(&mut self, pos: (f32, f32)) {
-        let relative_pos = (
-            self.relative_pos.0 + pos.0 * -10.0 / self.bit_size,
-            self.relative_pos.1 + pos.1 * -10.0 / self.bit_size,
-        );
-        self.relative_pos = relative_pos;
-        for chunk in self.chunks().into_iter() {
-            chunk.update_pos(relative_pos);
+        self.sync();
+        let cell = self.cell_at(sel_pos);
+        let state = if self.universe.get_state(cell.0, cell.1) == 0 {
+            self.color.clamp(1, self.universe.rule.colors.max(1))
+        } else {
+            0
+        };
+        if self.tool == Tool::Fill {
+            return match draw::fill(&self.universe, cell) {
+                Some(region) => {
+                    let cells: Vec<_> = region.into_iter().map(|(x, y)| (x, y, state)).collect();
+                    self.set_states(&cells);
+                    true
+                }
+                None => false,
+            };
         }
-        self.hover_chunk.update_relative_pos(relative_pos);
-    }
-    pub fn zoom(&mut self, zoom_in: bool) {
-        self.bit_size *= if zoom_in { 1.2 } else { 0.8 };
-        self.update_zoom();
+        let edits = if self.tool == Tool::Pencil {
+            self.apply(&[(cell.0, cell.1, state)])
+        } else {
+            Vec::new()
+        };
+        self.stroke = Some(Stroke {
+            tool: self.tool,
+            start: cell,
+            last: cell,
+            state,
+            edits,
+        });
+        true
     }
-    pub fn update_zoom(&mut self) {
-        let size = self.bit_size;
-        for chunk in self.chunks().into_iter() {
-            chunk.update_zoom(size);
+    // Carries a stroke on to the cell at `sel_pos`. The pencil paints along
+    // the way, so that a fast drag leaves no gaps.
+    pub fn drag(&mut self, sel_pos: (f32, f32)) {
+        let cell = self.cell_at(sel_pos);
+        let (tool, last, state) = match &self.stroke {
+            Some(stroke) if stroke.last != cell => (stroke.tool, stroke.last, stroke.state),
+            _ => return,
+        };
+        let edits = if tool == Tool::Pencil {
+            let cells: Vec<_> = draw::line(last, cell)
+                .into_iter()
+                .skip(1)
+                .map(|(x, y)| (x, y, state))
+                .collect();
+            self.apply(&cells)
+        } else {
+            Vec::new()
+        };
+        if let Some(stroke) = &mut self.stroke {
+            stroke.last = cell;
+            stroke.edits.extend(edits);
         }
-        self.hover_chunk.update_zoom(size);
     }
-    pub fn insert_chunk(&mut self, pos: [i32; 2], chunk: Chunk) {
-        self.map.insert(pos, chunk);
+    // Finishes a stroke, drawing the line or rectangle dragged out.
+    pub fn release(&mut self) {
+        let stroke = match self.stroke.take() {
+            Some(stroke) => stroke,
+            None => return,
+        };
+        let cells: Vec<_> = Game::shape(&stroke)
+            .into_iter()
+            .map(|(x, y)| (x, y, stroke.state))
+            .collect();
+        let mut edits = stroke.edits;
+        edits.extend(self.apply(&cells));
+        self.history.push(Edit::Cells(edits));
     }
-    pub fn draw(&mut self, window: &mut kiss3d::window::Window) {
-        let debug = self.debug;
-        for i in self.chunks() {
-            i.draw(window, debug);
+    // The cells the line or rectangle being dragged out would cover.
+    pub fn preview(&self) -> Vec<(i32, i32)> {
+        match &self.stroke {
+            Some(stroke) => Game::shape(stroke),
+            None => Vec::new(),
         }
-        self.hover_chunk.draw(window);
     }
-    pub fn chunks(&mut self) -> Vec<&mut Chunk> {
-        let mut vec: Vec<&mut Chunk> = Vec::new();
-        for i in self.map.values_mut().collect::<Vec<_>>() {
-            vec.push(i);
+    fn shape(stroke: &Stroke) -> Vec<(i32, i32)> {
+        match stroke.tool {
+            Tool::Line => draw::line(stroke.start, stroke.last),
+            Tool::Rectangle => draw::rectangle(stroke.start, stroke.last, false),
+            Tool::FilledRectangle => draw::rectangle(stroke.start, stroke.last, true),
+            _ => Vec::new(),
         }
-        vec
     }
-    pub fn chunks_from_map(map: &mut HashMap<[i32; 2], Chunk>) -> Vec<&mut Chunk> {
-        let mut vec: Vec<&mut Chunk> = Vec::new();
-        for i in map.values_mut().collect::<Vec<_>>() {
-            vec.push(i);
-        }
-        vec
-    }
-    pub fn edges(&self, pos: [i32; 2], corners: u8) -> Edges {
-        let edges = Edges {
-            left: match self.map.get(&[pos[0] - 1, pos[1]]) {
-                Some(j) => j.right(),
-                None => 0,
-            },
-            right: match self.map.get(&[pos[0] + 1, pos[1]]) {
-                Some(j) => j.left(),
-                None => 0,
-            },
-            top: match self.map.get(&[pos[0], pos[1] + 1]) {
-                Some(j) => j.bottom(),
-                None => 0,
-            },
-            bottom: match self.map.get(&[pos[0], pos[1] - 1]) {
-                Some(j) => j.top(),
-                None => 0,
-            },
-            corners: corners,
+    // Records the universe as it is now, so that whatever comes next (a run
+    // of generations, say) can be undone in one go.
+    pub fn checkpoint(&mut self) {
+        self.sync();
+        self.history
+            .push(Edit::Snapshot(Box::new(self.universe.clone())));
+    }
+    pub fn clear(&mut self) {
+        self.checkpoint();
+        self.universe.clear();
+    }
+    pub fn undo(&mut self) -> bool {
+        self.sync();
+        self.history.undo(&mut self.universe)
+    }
+    pub fn redo(&mut self) -> bool {
+        self.sync();
+        self.history.redo(&mut self.universe)
+    }
+    // Selects the rectangle between two cells at opposite corners.
+    pub fn select(&mut self, from: (i32, i32), to: (i32, i32)) {
+        self.selection = Some(Selection::new(from, to));
+    }
+    pub fn deselect(&mut self) {
+        self.selection = None;
+    }
+    // The cells inside the selection, moved so that its top left corner is
+    // at (0, 0).
+    pub fn selected_pattern(&mut self) -> Option<Pattern> {
+        let selection = self.selection?;
+        self.sync();
+        let mut pattern = self.universe.pattern();
+        pattern.cells.retain(|&(x, y)| selection.contains(x, y));
+        pattern.states.retain(|&(x, y, _)| selection.contains(x, y));
+        pattern.translate((-selection.left, -selection.top));
+        Some(pattern)
+    }
+    // Copies the selection to the clipboard as RLE and returns the text.
+    pub fn copy(&mut self) -> Option<String> {
+        let text = rle::write(&self.selected_pattern()?);
+        self.clipboard = Some(text.clone());
+        Some(text)
+    }
+    pub fn cut(&mut self) -> Option<String> {
+        let text = self.copy()?;
+        self.clear_selection();
+        Some(text)
+    }
+    // Starts pasting a pattern given as RLE text.
+    pub fn paste(&mut self, text: &str) -> Result<(), RleError> {
+        let mut pattern = rle::read_with(text, &self.tables)?;
+        pattern.normalize();
+        self.pasting = Some(pattern);
+        Ok(())
+    }
+    // Stamps a pattern from the built-in library, transformed, with its top
+    // left corner at (x, y), returning false if there is none by that name.
+    pub fn place_pattern(&mut self, name: &str, x: i32, y: i32, transform: Transform) -> bool {
+        let mut pattern = match library::get(name) {
+            Some(pattern) => pattern,
+            None => return false,
         };
-        edges
-    }
-    pub fn edges_from_map(map: &mut HashMap<[i32; 2], Chunk>, pos: [i32; 2], corners: u8) -> Edges {
-        let edges = Edges {
-            left: match map.get(&[pos[0] - 1, pos[1]]) {
-                Some(j) => j.right(),
-                None => 0,
-            },
-            right: match map.get(&[pos[0] + 1, pos[1]]) {
-                Some(j) => j.left(),
-                None => 0,
-            },
-            top: match map.get(&[pos[0], pos[1] + 1]) {
-                Some(j) => j.bottom(),
-                None => 0,
-            },
-            bottom: match map.get(&[pos[0], pos[1] - 1]) {
-                Some(j) => j.top(),
-                None => 0,
-            },
-            corners: corners,
+        pattern.transform(transform);
+        pattern.translate((x, y));
+        self.set_states(&pattern.all_states());
+        true
+    }
+    // Moves `step` entries on through the built-in library, wrapping around,
+    // and starts pasting the pattern there. Returns its name.
+    pub fn browse(&mut self, step: isize) -> &'static str {
+        let count = library::PATTERNS.len() as isize;
+        let index = match self.browsing {
+            Some(index) => index as isize + step,
+            None if step > 0 => step - 1,
+            None => count + step,
         };
-        edges
-    }
-    pub fn corners(&self, pos: [i32; 2]) -> u8 {
-        let map_clone = self.map.clone();
-        let mut chunks = [
-            self.map.get(&[pos[0] - 1, pos[1] - 1]),
-            self.map.get(&[pos[0] + 1, pos[1] - 1]),
-            self.map.get(&[pos[0] - 1, pos[1] + 1]),
-            self.map.get(&[pos[0] + 1, pos[1] + 1]),
-        ];
-        let mut corners = [
-            match chunks[0] {
-                Some(i) => i.get_bit_at_point((7, 0)),
-                None => false,
-            },
-            match chunks[1] {
-                Some(i) => i.get_bit_at_point((0, 0)),
-                None => false,
-            },
-            match chunks[2] {
-                Some(i) => i.get_bit_at_point((7, 7)),
-                None => false,
-            },
-            match chunks[3] {
-                Some(i) => i.get_bit_at_point((0, 7)),
-                None => false,
-            },
-        ];
-        let mut int: u8 = 0;
-        for i in 0..4 {
-            if corners[i] {
-                int |= (2 as u8).pow((7 - i) as u32);
-            }
-        }
-        int
-    }
-    pub fn iterate(&mut self, window: &mut kiss3d::window::Window) {
-        let mut activations: Vec<[i32; 2]> = Vec::new();
-        let mut map_clone = self.map.clone();
-        let mut edge_map: HashMap<[i32; 2], Edges> = HashMap::new();
-        for i in Game::chunks_from_map(&mut map_clone) {
-            let corners = self.corners(i.pos);
-            let edges = self.edges(i.pos, corners);
-            edge_map.insert(i.pos, edges);
+        let index = index.rem_euclid(count) as usize;
+        let (name, _) = library::PATTERNS[index];
+        self.browsing = Some(index);
+        self.pasting = library::get(name);
+        name
+    }
+    pub fn cancel_paste(&mut self) {
+        self.pasting = None;
+    }
+    // Rotates or flips the pattern being pasted, or else the cells in the
+    // selection, which then covers them in their new place. Returns false
+    // with neither.
+    pub fn transform(&mut self, transform: Transform) -> bool {
+        if let Some(pattern) = &mut self.pasting {
+            pattern.transform(transform);
+            return true;
         }
-        let mut stale_chunks: Vec<[i32; 2]> = Vec::new();
-        for i in self.chunks() {
-            let v = i.iterate(&edge_map.get(&i.pos).unwrap()); // interpret activations
-            Game::interpret_activations(v, &mut activations, i.pos);
-            if !i.active {
-                i.remove_nodes(window);
-                stale_chunks.push(i.pos);
+        let (selection, mut pattern) = match (self.selection, self.selected_pattern()) {
+            (Some(selection), Some(pattern)) => (selection, pattern),
+            _ => return false,
+        };
+        let mut cells: Vec<(i32, i32, u8)> = pattern
+            .all_states()
+            .into_iter()
+            .map(|(x, y, _)| (x + selection.left, y + selection.top, 0))
+            .collect();
+        let size = (selection.width(), selection.height());
+        pattern.transform_within(transform, size);
+        pattern.translate((selection.left, selection.top));
+        cells.extend(pattern.all_states());
+        self.set_states(&cells);
+        let corner = (selection.left, selection.top);
+        let (width, height) = if transform.swaps_sides() {
+            (size.1, size.0)
+        } else {
+            size
+        };
+        self.select(corner, (corner.0 + width - 1, corner.1 + height - 1));
+        true
+    }
+    // Replaces the cells in `region` with a random soup from `soup::soup`,
+    // as a single edit. Returns the part of the region it fills, which
+    // shares its top left corner.
+    pub fn soup(
+        &mut self,
+        region: Selection,
+        density: f64,
+        symmetry: Symmetry,
+        seed: u64,
+    ) -> Selection {
+        let mut pattern = soup::soup(region.width(), region.height(), density, symmetry, seed);
+        pattern.translate((region.left, region.top));
+        let (width, height) = symmetry.fit(region.width(), region.height());
+        self.sync();
+        let filled = Selection::new(
+            (region.left, region.top),
+            (region.left + width - 1, region.top + height - 1),
+        );
+        let mut cells = self.killing(|x, y| filled.contains(x, y));
+        let state = self.color.clamp(1, self.universe.rule.colors.max(1));
+        cells.extend(pattern.cells.iter().map(|&(x, y)| (x, y, state)));
+        self.set_states(&cells);
+        filled
+    }
+    // Kills every cell inside the selection, returning false without one.
+    pub fn clear_selection(&mut self) -> bool {
+        match self.selection {
+            Some(selection) => {
+                self.clear_where(|x, y| selection.contains(x, y));
+                true
             }
+            None => false,
         }
-        for i in stale_chunks {
-            self.map.remove(&i);
-        }
-        for i in activations {
-            match self.map.get(&i) {
-                Some(_) => {}
-                None => {
-                    self.map
-                        .insert(i, Chunk::new(i, self.bit_size, self.relative_pos));
-                }
+    }
+    // Kills every cell outside the selection, returning false without one.
+    pub fn clear_outside(&mut self) -> bool {
+        match self.selection {
+            Some(selection) => {
+                self.clear_where(|x, y| !selection.contains(x, y));
+                true
             }
+            None => false,
         }
     }
-    fn interpret_activations(v: u8, activations: &mut Vec<[i32; 2]>, pos: [i32; 2]) {
-        if Chunk::get_bit_at(v, 0) {
-            activations.push([pos[0] - 1, pos[1] + 1])
+    fn clear_where(&mut self, clear: impl Fn(i32, i32) -> bool) {
+        self.sync();
+        let cells = self.killing(clear);
+        self.set_states(&cells);
+    }
+    // Every cell that isn't dead and passes `kill`, set to dead.
+    fn killing(&self, kill: impl Fn(i32, i32) -> bool) -> Vec<(i32, i32, u8)> {
+        self.universe
+            .pattern()
+            .all_states()
+            .into_iter()
+            .filter(|&(x, y, _)| kill(x, y))
+            .map(|(x, y, _)| (x, y, 0))
+            .collect()
+    }
+    // Moves on to the next colour to paint with, wrapping to the first.
+    pub fn next_color(&mut self) {
+        let colors = self.universe.rule.colors.max(1);
+        self.color = self.color % colors + 1;
+    }
+    pub fn hover(&mut self, hover_pos: (f32, f32)) {
+        self.sync();
+        let cell = self.cell_at(hover_pos);
+        self.cursor = cell;
+        let (chunk, _) = Universe::cell_pos(cell.0, cell.1);
+        self.hover_chunk = if self.universe.map.contains_key(&chunk) {
+            None
+        } else {
+            Some(chunk)
+        };
+    }
+    pub fn pos(&mut self, pos: (f32, f32)) {
+        self.relative_pos = (
+            self.relative_pos.0 + pos.0 * -10.0 / self.bit_size,
+            self.relative_pos.1 + pos.1 * -10.0 / self.bit_size,
+        );
+    }
+    pub fn zoom(&mut self, zoom_in: bool) {
+        self.bit_size *= if zoom_in { 1.2 } else { 0.8 };
+    }
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.sync();
+        self.universe.set_rule(rule);
+    }
+    // Switches to a rule table, remembering it so patterns can name it.
+    pub fn set_table(&mut self, table: RuleTable) {
+        let table = Arc::new(table);
+        self.tables
+            .retain(|known| !known.name.eq_ignore_ascii_case(&table.name));
+        self.tables.push(Arc::clone(&table));
+        self.sync();
+        self.universe.set_table(table);
+    }
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.sync();
+        self.universe.set_topology(topology);
+    }
+    pub fn insert_chunk(&mut self, pos: [i32; 2], chunk: Chunk) {
+        self.sync();
+        self.universe.insert_chunk(pos, chunk);
+    }
+    pub fn set_chunk(&mut self, pos: [i32; 2], chunk: [Row; SIZE]) {
+        self.sync();
+        self.universe.set_chunk(pos, chunk);
+    }
+    pub fn iterate(&mut self) {
+        self.step(1);
+    }
+    // Advances `generations` generations with `engine_in_use`, or fewer
+    // when that many could carry cells off the edge of the i32 plane, and
+    // returns how many it advanced. HashLife steps leave `universe` behind
+    // until `sync`.
+    pub fn step(&mut self, generations: u64) -> u64 {
+        let generations = generations.min(self.reach());
+        if self.engine_in_use() == EngineKind::Chunks {
+            self.sync();
+            self.rewind.record(&self.universe);
+            self.universe.step(generations);
+            return generations;
         }
-        if Chunk::get_bit_at(v, 1) {
-            activations.push([pos[0], pos[1] + 1])
+        if !self.hashlife_ahead {
+            self.rewind.record(&self.universe);
         }
-        if Chunk::get_bit_at(v, 2) {
-            activations.push([pos[0] + 1, pos[1] + 1])
+        let rule = self.universe.rule;
+        let mut life = match self.hashlife.take() {
+            Some(life) if life.rule == rule => life,
+            _ => HashLife::new(rule),
+        };
+        if !self.hashlife_ahead && self.hashlife_revision != Some(self.universe.revision()) {
+            life.load(&self.universe.cells(), self.universe.generation);
         }
-        if Chunk::get_bit_at(v, 3) {
-            activations.push([pos[0] - 1, pos[1]])
+        life.step(generations);
+        self.hashlife = Some(life);
+        self.hashlife_ahead = true;
+        generations
+    }
+    // Brings `universe` up to date after HashLife steps. Everything here that
+    // reads or changes the cells does this first, and the viewer does before
+    // drawing.
+    pub fn sync(&mut self) {
+        let life = match &self.hashlife {
+            Some(life) if self.hashlife_ahead => life,
+            _ => return,
+        };
+        self.universe.map = HashMap::new();
+        self.universe.map.insert([0, 0], Chunk::new([0, 0]));
+        for (x, y) in life.cells() {
+            self.universe.set_cell(x, y, true);
         }
-        if Chunk::get_bit_at(v, 4) {
-            activations.push([pos[0] + 1, pos[1]])
+        self.universe.generation = life.generation();
+        self.universe.touch();
+        self.hashlife_revision = Some(self.universe.revision());
+        self.hashlife_ahead = false;
+    }
+    // The current generation, which HashLife may have taken past the
+    // universe's.
+    pub fn generation(&self) -> u64 {
+        match &self.hashlife {
+            Some(life) if self.hashlife_ahead => life.generation(),
+            _ => self.universe.generation,
         }
-        if Chunk::get_bit_at(v, 5) {
-            activations.push([pos[0] - 1, pos[1] - 1])
+    }
+    // How many generations can pass before cells spreading from the live
+    // area at the rule's speed of light could leave the i32 plane, keeping a
+    // chunk clear of the edge.
+    fn reach(&self) -> u64 {
+        if self.universe.topology.is_bounded() {
+            return u64::MAX;
         }
-        if Chunk::get_bit_at(v, 6) {
-            activations.push([pos[0], pos[1] - 1])
+        let (left, top, right, bottom) = match &self.hashlife {
+            Some(life) if self.hashlife_ahead => life.extent(),
+            _ => {
+                let size = SIZE as i64;
+                let mut keys = self.universe.map.keys();
+                let first = match keys.next() {
+                    Some(&[x, y]) => (x, y, x, y),
+                    None => return u64::MAX,
+                };
+                let (min_x, min_y, max_x, max_y) = keys.fold(first, |b, &[x, y]| {
+                    (b.0.min(x), b.1.min(y), b.2.max(x), b.3.max(y))
+                });
+                // chunk keys grow upwards
+                (
+                    min_x as i64 * size,
+                    -max_y as i64 * size,
+                    max_x as i64 * size + size - 1,
+                    -min_y as i64 * size + size - 1,
+                )
+            }
+        };
+        let margin = SIZE as i64;
+        let room =
+            (left.min(top) - i32::MIN as i64).min(i32::MAX as i64 - right.max(bottom)) - margin;
+        (room.max(0) / self.universe.rule.radius().max(1) as i64) as u64
+    }
+    // Returns how many generations it advanced, as `step` does.
+    pub fn jump(&mut self) -> u64 {
+        self.step(1 << self.jump_exponent)
+    }
+    // Whether HashLife can step the universe. It only models two states and
+    // one-cell neighbourhoods on the infinite plane, so bounded worlds,
+    // Generations, Larger than Life, coloured rules and rule tables always
+    // use chunks.
+    pub fn hashlife_runs(&self) -> bool {
+        let rule = &self.universe.rule;
+        !(self.universe.topology.is_bounded()
+            || self.universe.table.is_some()
+            || rule.is_generations()
+            || rule.range.is_some()
+            || rule.is_colored())
+    }
+    // The engine `step` uses: the selected one, unless HashLife can't run.
+    pub fn engine_in_use(&self) -> EngineKind {
+        if self.hashlife_runs() {
+            self.engine
+        } else {
+            EngineKind::Chunks
         }
-        if Chunk::get_bit_at(v, 7) {
-            activations.push([pos[0] + 1, pos[1] - 1])
+    }
+    // Goes back `generations` generations, returning false when nothing
+    // retained is that old. This can be undone, and the generations skipped
+    // over stay retained.
+    pub fn step_back(&mut self, generations: u64) -> bool {
+        self.sync();
+        let target = match self.universe.generation.checked_sub(generations) {
+            Some(target) => target,
+            None => return false,
+        };
+        self.rewind_to(target)
+    }
+    // Goes to `generation`, or the newest one known when it is later, by
+    // stepping on from the latest retained generation no later than it.
+    // Stays put when that is the current one.
+    pub fn rewind_to(&mut self, generation: u64) -> bool {
+        self.sync();
+        let current = self.universe.generation;
+        let newest = self.rewind.generations().last().copied();
+        if current <= generation && newest.map_or(true, |newest| newest <= current) {
+            return true;
         }
+        let generation = generation.min(newest.unwrap_or(0).max(current));
+        let mut frame = match self.rewind.find(generation) {
+            Some(frame) => frame.clone(),
+            None => return false,
+        };
+        self.checkpoint();
+        self.rewind.keep(self.universe.clone());
+        frame.threads = self.universe.threads;
+        // frames are up to a stride apart
+        frame.step(generation - frame.generation);
+        self.universe = frame;
+        true
+    }
+    // Switches between the engines, returning false and leaving chunks
+    // selected when HashLife can't run.
+    pub fn toggle_engine(&mut self) -> bool {
+        self.sync();
+        self.engine = match self.engine {
+            EngineKind::Chunks if !self.hashlife_runs() => return false,
+            EngineKind::Chunks => EngineKind::HashLife,
+            EngineKind::HashLife => EngineKind::Chunks,
+        };
+        true
     }
-    pub fn set_chunk(&mut self, pos: [i32; 2], chunk: [u8; 8]) {
-        self.map.get_mut(&pos).unwrap().set(chunk);
-    }
-    pub fn save(&mut self) -> std::io::Result<()> {
-        let mut file = File::create("save.cgl")?;
-        let mut data: Vec<u8> = vec![];
-        for chunk in self.chunks() {
-            let pos = chunk.pos;
-            let mut res1: Vec<u8> = [pos[0].to_ne_bytes(), pos[1].to_ne_bytes()].concat();
-            let mut res2: Vec<u8> = [res1, chunk.chunk.to_vec()].concat();
-            file.write_all(&res2)?;
+    // Places a pattern's cells shifted by `offset`, taking on its rule and
+    // metadata.
+    pub fn load_pattern(&mut self, pattern: Pattern, offset: (i32, i32)) {
+        self.sync();
+        if let Some(table) = &pattern.table {
+            self.universe.set_table(Arc::clone(table));
+        } else if let Some(rule) = pattern.rule {
+            self.set_rule(rule);
         }
-        println!("{:?}", file.metadata());
+        self.universe.place(&pattern, offset);
+        self.name = pattern.name;
+        self.comments = pattern.comments;
+    }
+    pub fn pattern(&mut self) -> Pattern {
+        self.sync();
+        let mut pattern = self.universe.pattern();
+        pattern.name = self.name.clone();
+        pattern.comments = self.comments.clone();
+        pattern
+    }
+    pub fn load_rle(&mut self, text: &str, offset: (i32, i32)) -> Result<(), RleError> {
+        let pattern = rle::read_with(text, &self.tables)?;
+        self.checkpoint();
+        self.load_pattern(pattern, offset);
         Ok(())
     }
-    pub fn open(&mut self, window: &mut kiss3d::window::Window) -> std::io::Result<()> {
-        let mut file = File::open("save.cgl")?;
-        let mut bytes: Vec<u8> = vec![];
-        for chunk in self.chunks() {
-            chunk.remove_nodes(window);
+    pub fn to_rle(&mut self) -> String {
+        rle::write(&self.pattern())
+    }
+    pub fn save_data(&mut self) -> SaveData {
+        self.sync();
+        let mut chunks: Vec<Chunk> = self.universe.map.values().cloned().collect();
+        chunks.sort_unstable_by_key(|chunk| chunk.pos);
+        SaveData {
+            rule: self.universe.rule,
+            table: self.universe.table.clone(),
+            generation: self.universe.generation,
+            bit_size: self.bit_size,
+            relative_pos: self.relative_pos,
+            topology: self.universe.topology,
+            chunks,
         }
-        self.map = HashMap::new();
-        match file.read_to_end(&mut bytes) {
-            Ok(_usize) => {}
-            Err(e) => {
-                println!("{}", e);
-                return Err(e);
+    }
+    pub fn load_save_data(&mut self, data: SaveData) {
+        self.hashlife_ahead = false;
+        self.universe.touch();
+        self.universe.map = HashMap::new();
+        self.universe.rule = data.rule;
+        self.universe.table = data.table;
+        self.universe.generation = data.generation;
+        self.universe.topology = data.topology;
+        for chunk in data.chunks {
+            self.universe.insert_chunk(chunk.pos, chunk);
+        }
+        self.bit_size = data.bit_size;
+        self.relative_pos = data.relative_pos;
+    }
+    pub fn save(&mut self, path: &Path) -> Result<(), FileError> {
+        let bytes = match FileFormat::from_path(path) {
+            Some(FileFormat::Cgl) => save::write(&self.save_data()),
+            Some(FileFormat::Rle) => rle::write(&self.pattern()).into_bytes(),
+            Some(FileFormat::Plaintext) => plaintext::write(&self.pattern()).into_bytes(),
+            Some(FileFormat::Life106) => life106::write(&self.pattern()).into_bytes(),
+            Some(FileFormat::Table) | None => {
+                return Err(FileError::UnknownFormat(path.to_path_buf()))
             }
+        };
+        fs::write(path, bytes)?;
+        self.path = Some(path.to_path_buf());
+        Ok(())
+    }
+    // Replaces the universe with the contents of a file, which can be
+    // undone. Patterns from .rle, .cells and .lif files are centred on the
+    // origin. A .rule file only switches to its rule table, keeping the
+    // cells.
+    pub fn open(&mut self, path: &Path) -> Result<(), FileError> {
+        let format = match FileFormat::from_path(path) {
+            Some(format) => format,
+            None => return Err(FileError::UnknownFormat(path.to_path_buf())),
+        };
+        if format == FileFormat::Table {
+            let table = RuleTable::parse(&fs::read_to_string(path)?)?;
+            self.checkpoint();
+            self.set_table(table);
+            return Ok(());
         }
-        for i in 0..(bytes.len() / 16) {
-            let pos = (
-                i32::from_ne_bytes(clone_into_array(&bytes[i * 16..i * 16 + 4])),
-                i32::from_ne_bytes(clone_into_array(&bytes[i * 16 + 4..i * 16 + 8])),
-            );
-            let chunk: [u8; 8] = clone_into_array(&bytes[i * 16 + 8..i * 16 + 16]);
-            // println!("pos: {:?}, data: {:?}", pos, chunk);
-            self.map.insert(
-                [pos.0, pos.1],
-                Chunk::from([pos.0, pos.1], chunk, 10.0, self.relative_pos),
-            );
+        if format == FileFormat::Cgl {
+            let data = save::read(&fs::read(path)?)?;
+            self.checkpoint();
+            self.load_save_data(data);
+            self.rewind.clear();
+            self.name = None;
+            self.comments = Vec::new();
+        } else {
+            let text = fs::read_to_string(path)?;
+            let pattern = match format {
+                FileFormat::Rle => rle::read_with(&text, &self.tables)?,
+                FileFormat::Plaintext => plaintext::read(&text)?,
+                _ => life106::read(&text)?,
+            };
+            self.checkpoint();
+            // the middle of the bounding box, which Life 1.06 files can put
+            // anywhere
+            let middle = |min: i32, max: i32| ((min as i64 + max as i64) / 2) as i32;
+            let offset = match pattern.bounding_box() {
+                Some((min_x, min_y, max_x, max_y)) => {
+                    (-middle(min_x, max_x), -middle(min_y, max_y))
+                }
+                None => (0, 0),
+            };
+            self.hashlife_ahead = false;
+            self.universe.map = HashMap::new();
+            self.universe.generation = 0;
+            self.universe.touch();
+            self.rewind.clear();
+            self.load_pattern(pattern, offset);
+            self.bit_size = 10.0;
+            self.relative_pos = (0.0, 0.0);
         }
-        self.relative_pos = (0.0, 0.0);
-        self.bit_size = 10.0;
+        self.path = Some(path.to_path_buf());
         Ok(())
     }
 }
-
-use std::convert::AsMut;
-
-fn clone_into_array<A, T>(slice: &[T]) -> A
-where
-    A: Default + AsMut<[T]>,
-    T: Clone,
-{
-    let mut a = A::default();
-    <A as AsMut<[T]>>::as_mut(&mut a).clone_from_slice(slice);
-    a
-}
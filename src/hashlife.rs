@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::engine::Engine;
+use crate::rule::Rule;
+
+// HashLife: the universe is a quadtree of canonical (hash-consed) nodes, and
+// the future of each node is memoised, so repetitive patterns can be advanced
+// by 2^k generations in time roughly proportional to k.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct NodeId(u32);
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    // nw, ne, sw, se
+    children: [NodeId; 4],
+    population: u64,
+}
+
+const DEAD: NodeId = NodeId(0);
+const ALIVE: NodeId = NodeId(1);
+
+pub struct HashLife {
+    pub rule: Rule,
+    nodes: Vec<Node>,
+    lookup: HashMap<[NodeId; 4], NodeId>,
+    empty: Vec<NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    root: NodeId,
+    // Cell coordinates of the root's top left corner.
+    origin: (i64, i64),
+    generation: u64,
+}
+impl HashLife {
+    // Node count past which unreachable nodes and memoised results are dropped.
+    pub const NODE_LIMIT: usize = 1 << 22;
+    pub fn new(rule: Rule) -> HashLife {
+        let leaf = |population| Node {
+            level: 0,
+            children: [DEAD; 4],
+            population,
+        };
+        let mut life = HashLife {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            lookup: HashMap::new(),
+            empty: vec![DEAD],
+            results: HashMap::new(),
+            root: DEAD,
+            origin: (0, 0),
+            generation: 0,
+        };
+        life.root = life.empty(3);
+        life.origin = (-4, -4);
+        life
+    }
+    pub fn from_cells(rule: Rule, cells: &[(i32, i32)]) -> HashLife {
+        let mut life = HashLife::new(rule);
+        life.load(cells, 0);
+        life
+    }
+    // Replaces the pattern, keeping memoised results for reuse.
+    pub fn load(&mut self, cells: &[(i32, i32)], generation: u64) {
+        if self.nodes.len() > HashLife::NODE_LIMIT {
+            *self = HashLife::new(self.rule);
+        }
+        self.root = self.empty(3);
+        self.origin = (-4, -4);
+        self.generation = generation;
+        for &(x, y) in cells {
+            self.set_cell(x, y, true);
+        }
+    }
+    pub fn level(&self) -> u8 {
+        self.node(self.root).level
+    }
+    // The square the root covers as (left, top, right, bottom). Every live
+    // cell lies inside it.
+    pub fn extent(&self) -> (i64, i64, i64, i64) {
+        let last = (1i64 << self.level()) - 1;
+        let (x, y) = self.origin;
+        (x, y, x + last, y + last)
+    }
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.node(id).children
+    }
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.lookup.get(&children) {
+            return id;
+        }
+        let level = self.node(children[0]).level + 1;
+        let population = children.iter().map(|&c| self.node(c).population).sum();
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(Node {
+            level,
+            children,
+            population,
+        });
+        self.lookup.insert(children, id);
+        id
+    }
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let next = self.join([e; 4]);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+    // Grows the root by one level, keeping the pattern centred.
+    fn expand(&mut self) {
+        let level = self.level();
+        let e = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let nw = self.join([e, e, e, nw]);
+        let ne = self.join([e, e, ne, e]);
+        let sw = self.join([e, sw, e, e]);
+        let se = self.join([se, e, e, e]);
+        self.root = self.join([nw, ne, sw, se]);
+        let half = 1i64 << (level - 1);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+    }
+    fn contains(&self, x: i64, y: i64) -> bool {
+        let size = 1i64 << self.level();
+        x >= self.origin.0
+            && y >= self.origin.1
+            && x < self.origin.0 + size
+            && y < self.origin.1 + size
+    }
+    fn get(&self, id: NodeId, x: i64, y: i64) -> bool {
+        let node = self.node(id);
+        if node.population == 0 {
+            return false;
+        }
+        if node.level == 0 {
+            return id == ALIVE;
+        }
+        let half = 1i64 << (node.level - 1);
+        let i = (x >= half) as usize + 2 * (y >= half) as usize;
+        self.get(node.children[i], x % half, y % half)
+    }
+    fn set(&mut self, id: NodeId, x: i64, y: i64, val: bool) -> NodeId {
+        let node = *self.node(id);
+        if node.level == 0 {
+            return if val { ALIVE } else { DEAD };
+        }
+        let half = 1i64 << (node.level - 1);
+        let i = (x >= half) as usize + 2 * (y >= half) as usize;
+        let mut children = node.children;
+        children[i] = self.set(children[i], x % half, y % half, val);
+        self.join(children)
+    }
+    // Centre of a level >= 2 node, one level down.
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        let children = [
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ];
+        self.join(children)
+    }
+    // The centre half of a level-2 node advanced one generation.
+    fn base_case(&mut self, id: NodeId) -> NodeId {
+        let mut grid = [[false; 4]; 4];
+        for (i, &quadrant) in self.children(id).iter().enumerate() {
+            for (j, &leaf) in self.children(quadrant).iter().enumerate() {
+                let x = (i % 2) * 2 + j % 2;
+                let y = (i / 2) * 2 + j / 2;
+                grid[y][x] = leaf == ALIVE;
+            }
+        }
+        let mut result = [DEAD; 4];
+        for (i, cell) in result.iter_mut().enumerate() {
+            let (x, y) = (1 + i % 2, 1 + i / 2);
+            // the live neighbours, NW to SE from bit 0
+            let mut config: u8 = 0;
+            let mut bit = 0;
+            for (dy, row) in grid.iter().enumerate().take(y + 2).skip(y - 1) {
+                for (dx, &alive) in row.iter().enumerate().take(x + 2).skip(x - 1) {
+                    if (dx, dy) != (x, y) {
+                        config |= (alive as u8) << bit;
+                        bit += 1;
+                    }
+                }
+            }
+            if self.rule.next_cell(grid[y][x], config) {
+                *cell = ALIVE;
+            }
+        }
+        self.join(result)
+    }
+    // The centre half of a level L node advanced 2^k generations, k <= L - 2.
+    fn advance(&mut self, id: NodeId, k: u8) -> NodeId {
+        let level = self.node(id).level;
+        if self.node(id).population == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, k)) {
+            return result;
+        }
+        let result = if level == 2 {
+            self.base_case(id)
+        } else {
+            let [nw, ne, sw, se] = self.children(id);
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+            // nine overlapping level L-1 squares covering the node
+            let parts = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+            let mut inner = [DEAD; 9];
+            for (part, out) in parts.iter().zip(inner.iter_mut()) {
+                *out = if k == level - 2 {
+                    self.advance(*part, level - 3)
+                } else {
+                    self.centre(*part)
+                };
+            }
+            let step = k.min(level - 3);
+            let mut quarters = [DEAD; 4];
+            for (i, quarter) in quarters.iter_mut().enumerate() {
+                let (x, y) = (i % 2, i / 2);
+                let at = |dx: usize, dy: usize| inner[(y + dy) * 3 + x + dx];
+                let joined = self.join([at(0, 0), at(1, 0), at(0, 1), at(1, 1)]);
+                *quarter = self.advance(joined, step);
+            }
+            self.join(quarters)
+        };
+        self.results.insert((id, k), result);
+        result
+    }
+    // Whether every live cell is inside the centred square a quarter of the
+    // root's side, made of the innermost grandchild of each quadrant.
+    fn is_padded(&self) -> bool {
+        if self.level() < 3 {
+            return false;
+        }
+        let [nw, ne, sw, se] = self.children(self.root);
+        let inner = self.node(self.children(self.children(nw)[3])[3]).population
+            + self.node(self.children(self.children(ne)[2])[2]).population
+            + self.node(self.children(self.children(sw)[1])[1]).population
+            + self.node(self.children(self.children(se)[0])[0]).population;
+        inner == self.node(self.root).population
+    }
+    // Advances the pattern by 2^k generations.
+    pub fn step_pow2(&mut self, k: u8) {
+        while self.level() < k + 3 || !self.is_padded() {
+            self.expand();
+        }
+        let level = self.level();
+        self.root = self.advance(self.root, k);
+        let quarter = 1i64 << (level - 2);
+        self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+        self.generation += 1 << k;
+        if self.nodes.len() > HashLife::NODE_LIMIT {
+            self.collect_garbage();
+        }
+    }
+    // Rebuilds the node store from the nodes reachable from the root.
+    pub fn collect_garbage(&mut self) {
+        let mut fresh = HashLife::new(self.rule);
+        let mut copied: HashMap<NodeId, NodeId> = HashMap::new();
+        fresh.root = fresh.copy_from(self, self.root, &mut copied);
+        fresh.origin = self.origin;
+        fresh.generation = self.generation;
+        *self = fresh;
+    }
+    fn copy_from(
+        &mut self,
+        other: &HashLife,
+        id: NodeId,
+        copied: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if id == DEAD || id == ALIVE {
+            return id;
+        }
+        if let Some(&new) = copied.get(&id) {
+            return new;
+        }
+        let mut children = other.children(id);
+        for child in children.iter_mut() {
+            *child = self.copy_from(other, *child, copied);
+        }
+        let new = self.join(children);
+        copied.insert(id, new);
+        new
+    }
+    fn collect_cells(&self, id: NodeId, x: i64, y: i64, out: &mut Vec<(i32, i32)>) {
+        let node = self.node(id);
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            // cells that have left the i32 plane can't be reported
+            if let (Ok(x), Ok(y)) = (i32::try_from(x), i32::try_from(y)) {
+                out.push((x, y));
+            }
+            return;
+        }
+        let half = 1i64 << (node.level - 1);
+        for (i, &child) in node.children.iter().enumerate() {
+            let dx = (i % 2) as i64 * half;
+            let dy = (i / 2) as i64 * half;
+            self.collect_cells(child, x + dx, y + dy, out);
+        }
+    }
+    // Bounding box relative to the node's corner, memoised per node.
+    fn bounds(
+        &self,
+        id: NodeId,
+        memo: &mut HashMap<NodeId, (i64, i64, i64, i64)>,
+    ) -> Option<(i64, i64, i64, i64)> {
+        let node = self.node(id);
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some((0, 0, 0, 0));
+        }
+        if let Some(&b) = memo.get(&id) {
+            return Some(b);
+        }
+        let half = 1i64 << (node.level - 1);
+        let mut result: Option<(i64, i64, i64, i64)> = None;
+        for (i, &child) in node.children.iter().enumerate() {
+            if let Some(b) = self.bounds(child, memo) {
+                let dx = (i % 2) as i64 * half;
+                let dy = (i / 2) as i64 * half;
+                let b = (b.0 + dx, b.1 + dy, b.2 + dx, b.3 + dy);
+                result = Some(match result {
+                    Some(r) => (r.0.min(b.0), r.1.min(b.1), r.2.max(b.2), r.3.max(b.3)),
+                    None => b,
+                });
+            }
+        }
+        let result = result.unwrap();
+        memo.insert(id, result);
+        Some(result)
+    }
+}
+impl Engine for HashLife {
+    fn get_cell(&self, x: i32, y: i32) -> bool {
+        let (x, y) = (x as i64, y as i64);
+        self.contains(x, y) && self.get(self.root, x - self.origin.0, y - self.origin.1)
+    }
+    fn set_cell(&mut self, x: i32, y: i32, val: bool) {
+        let (x, y) = (x as i64, y as i64);
+        while !self.contains(x, y) {
+            self.expand();
+        }
+        self.root = self.set(self.root, x - self.origin.0, y - self.origin.1, val);
+    }
+    fn cells(&self) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+        self.collect_cells(self.root, self.origin.0, self.origin.1, &mut cells);
+        cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        cells
+    }
+    fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+    fn bounding_box(&self) -> Option<(i32, i32, i32, i32)> {
+        let b = self.bounds(self.root, &mut HashMap::new())?;
+        // clamped to the i32 plane, which the pattern may have outgrown
+        let clamp = |v: i64| i32::try_from(v).unwrap_or(if v < 0 { i32::MIN } else { i32::MAX });
+        Some((
+            clamp(b.0 + self.origin.0),
+            clamp(b.1 + self.origin.1),
+            clamp(b.2 + self.origin.0),
+            clamp(b.3 + self.origin.1),
+        ))
+    }
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+    fn step(&mut self, generations: u64) {
+        for k in 0..64 {
+            if generations & (1 << k) != 0 {
+                self.step_pow2(k);
+            }
+        }
+    }
+}
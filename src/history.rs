@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+
+use crate::universe::Universe;
+
+// One undoable change. Small edits keep each cell's state before and after;
+// anything that touches the whole universe keeps a copy of it instead.
+#[derive(Clone)]
+pub enum Edit {
+    Cells(Vec<CellEdit>),
+    Snapshot(Box<Universe>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellEdit {
+    pub x: i32,
+    pub y: i32,
+    pub before: u8,
+    pub after: u8,
+}
+
+impl Edit {
+    // Chunks held by a copy of the universe, which is what makes an edit
+    // large.
+    fn chunks(&self) -> usize {
+        match self {
+            Edit::Cells(_) => 0,
+            Edit::Snapshot(universe) => universe.map.len(),
+        }
+    }
+    // Puts the universe back the way it was before the edit and returns the
+    // edit that redoes it.
+    fn revert(self, universe: &mut Universe) -> Edit {
+        match self {
+            Edit::Cells(cells) => {
+                for cell in cells.iter().rev() {
+                    universe.set_state(cell.x, cell.y, cell.before);
+                }
+                Edit::Cells(
+                    cells
+                        .into_iter()
+                        .map(|cell| CellEdit {
+                            before: cell.after,
+                            after: cell.before,
+                            ..cell
+                        })
+                        .collect(),
+                )
+            }
+            Edit::Snapshot(mut snapshot) => {
+                snapshot.threads = universe.threads;
+                std::mem::swap(universe, &mut snapshot);
+                Edit::Snapshot(snapshot)
+            }
+        }
+    }
+}
+
+// Undo and redo stacks. At most `limit` edits are kept for undoing, and the
+// snapshots among them hold at most `budget` chunks between them, so a few
+// copies of a huge universe can't use memory without bound. The oldest edits
+// are dropped first, but the latest is kept whatever its size.
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    pub limit: usize,
+    pub budget: usize,
+    // Chunks held by the snapshots in `undo`.
+    chunks: usize,
+}
+impl History {
+    pub const DEFAULT_LIMIT: usize = 100;
+    pub const DEFAULT_BUDGET: usize = 1 << 16;
+    pub fn new(limit: usize) -> History {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+            budget: History::DEFAULT_BUDGET,
+            chunks: 0,
+        }
+    }
+    // Records an edit that has just been made, forgetting anything undone.
+    pub fn push(&mut self, edit: Edit) {
+        if let Edit::Cells(cells) = &edit {
+            if cells.is_empty() {
+                return;
+            }
+        }
+        self.redo.clear();
+        self.chunks += edit.chunks();
+        self.undo.push_back(edit);
+        self.trim();
+    }
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+    fn trim(&mut self) {
+        while self.undo.len() > self.limit || (self.chunks > self.budget && self.undo.len() > 1) {
+            if let Some(edit) = self.undo.pop_front() {
+                self.chunks -= edit.chunks();
+            }
+        }
+    }
+    // Reverts the latest edit, returning false when there is none.
+    pub fn undo(&mut self, universe: &mut Universe) -> bool {
+        match self.undo.pop_back() {
+            Some(edit) => {
+                self.chunks -= edit.chunks();
+                self.redo.push(edit.revert(universe));
+                true
+            }
+            None => false,
+        }
+    }
+    // Reapplies the latest undone edit, returning false when there is none.
+    pub fn redo(&mut self, universe: &mut Universe) -> bool {
+        match self.redo.pop() {
+            Some(edit) => {
+                let edit = edit.revert(universe);
+                self.chunks += edit.chunks();
+                self.undo.push_back(edit);
+                self.trim();
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.chunks = 0;
+    }
+}
+impl Default for History {
+    fn default() -> History {
+        History::new(History::DEFAULT_LIMIT)
+    }
+}
+
+// Copies of the universe from just before recent steps, oldest first, so
+// that the viewer can go back to a generation that came before. Life can't be
+// run backwards, so copies are only taken every `stride` generations (the
+// ones between can be stepped to again from there), and only the last
+// `capacity` of them, holding at most `budget` chunks between them, are
+// retained. The newest is kept whatever its size.
+pub struct Rewind {
+    frames: VecDeque<Universe>,
+    pub capacity: usize,
+    pub stride: u64,
+    pub budget: usize,
+}
+impl Rewind {
+    pub const DEFAULT_CAPACITY: usize = 100;
+    pub const DEFAULT_STRIDE: u64 = 10;
+    pub const DEFAULT_BUDGET: usize = 1 << 16;
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            frames: VecDeque::new(),
+            capacity,
+            stride: Rewind::DEFAULT_STRIDE,
+            budget: Rewind::DEFAULT_BUDGET,
+        }
+    }
+    // Records the universe about to be stepped, unless the last copy is less
+    // than `stride` generations older. Anything retained from its generation
+    // on belongs to a future that is being replaced.
+    pub fn record(&mut self, universe: &Universe) {
+        while let Some(frame) = self.frames.back() {
+            if frame.generation < universe.generation {
+                break;
+            }
+            self.frames.pop_back();
+        }
+        if let Some(frame) = self.frames.back() {
+            if universe.generation - frame.generation < self.stride.max(1) {
+                return;
+            }
+        }
+        self.frames.push_back(universe.clone());
+        self.trim();
+    }
+    // Keeps the universe without forgetting later generations, so the
+    // viewer can come back to it after rewinding.
+    pub fn keep(&mut self, universe: Universe) {
+        let generation = universe.generation;
+        let at = self
+            .frames
+            .iter()
+            .position(|frame| frame.generation >= generation)
+            .unwrap_or(self.frames.len());
+        match self.frames.get_mut(at) {
+            Some(frame) if frame.generation == generation => *frame = universe,
+            _ => self.frames.insert(at, universe),
+        }
+        self.trim();
+    }
+    // The latest retained universe from no later than `generation`.
+    pub fn find(&self, generation: u64) -> Option<&Universe> {
+        self.frames
+            .iter()
+            .rev()
+            .find(|frame| frame.generation <= generation)
+    }
+    pub fn generations(&self) -> Vec<u64> {
+        self.frames.iter().map(|frame| frame.generation).collect()
+    }
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+    pub fn set_stride(&mut self, stride: u64) {
+        self.stride = stride;
+    }
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+    fn trim(&mut self) {
+        let mut chunks: usize = self.frames.iter().map(|frame| frame.map.len()).sum();
+        while self.frames.len() > self.capacity || (chunks > self.budget && self.frames.len() > 1) {
+            if let Some(frame) = self.frames.pop_front() {
+                chunks -= frame.map.len();
+            }
+        }
+    }
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+impl Default for Rewind {
+    fn default() -> Rewind {
+        Rewind::new(Rewind::DEFAULT_CAPACITY)
+    }
+}
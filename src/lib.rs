@@ -1,26 +1,60 @@
-mod chunk;
-mod game;
-
-use chunk::Chunk;
-use chunk::Edges;
-use game::Game;
+pub mod chunk;
+pub mod draw;
+pub mod engine;
+pub mod files;
+pub mod game;
+pub mod hashlife;
+pub mod history;
+pub mod library;
+pub mod life106;
+pub mod pattern;
+pub mod plaintext;
+pub mod rle;
+pub mod rule;
+pub mod save;
+pub mod selection;
+pub mod soup;
+pub mod table;
+pub mod topology;
+pub mod universe;
+#[cfg(feature = "gui")]
+pub mod view;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chunk::Chunk;
+    use chunk::Edges;
+    use chunk::Row;
+    use chunk::SIZE;
+    use engine::Engine;
+    use engine::EngineKind;
+    use game::Game;
+    use hashlife::HashLife;
+    use pattern::Pattern;
+    use rule::ParseRuleError;
+    use rule::Rule;
+    use std::collections::HashMap;
+    use topology::ParseTopologyError;
+    use topology::Topology;
+    use universe::Universe;
     #[test]
     fn get_bit() {
         let byte = 0b1000_0010;
-        assert_eq!(Chunk::get_bit_at(byte, 0), true);
-        assert_eq!(Chunk::get_bit_at(byte, 1), false);
-        assert_eq!(Chunk::get_bit_at(byte, 6), true);
-        assert_eq!(Chunk::get_bit_at(byte, 7), false);
+        assert!(Chunk::get_bit_at(byte, 0));
+        assert!(!Chunk::get_bit_at(byte, 1));
+        assert!(Chunk::get_bit_at(byte, 6));
+        assert!(!Chunk::get_bit_at(byte, 7));
+        let row: Row = 1 << (SIZE - 1) | 1;
+        assert!(Chunk::get_bit_in_row(row, 0));
+        assert!(!Chunk::get_bit_in_row(row, 1));
+        assert!(Chunk::get_bit_in_row(row, SIZE as u8 - 1));
     }
     #[test]
     fn get_bit_point() {
         let chunk = Chunk::from(
             [0, 0],
-            [
+            rows([
                 0b0100_0000,
                 0b0000_0000,
                 0b0000_0000,
@@ -29,19 +63,18 @@ mod tests {
                 0b0000_0000,
                 0b0000_0000,
                 0b0010_0000,
-            ],
-            10.0,
+            ]),
         );
-        assert_eq!(chunk.get_bit_at_point((0, 0)), false);
-        assert_eq!(chunk.get_bit_at_point((1, 0)), true);
-        assert_eq!(chunk.get_bit_at_point((0, 1)), false);
-        assert_eq!(chunk.get_bit_at_point((2, 7)), true);
+        assert!(!chunk.get_bit_at_point((0, 0)));
+        assert!(chunk.get_bit_at_point((1, 0)));
+        assert!(!chunk.get_bit_at_point((0, 1)));
+        assert!(chunk.get_bit_at_point((2, 7)));
     }
     #[test]
     fn get_byte() {
         let chunk = Chunk::from(
             [0, 0],
-            [
+            rows([
                 0b0000_0001,
                 0b0000_0010,
                 0b0000_0100,
@@ -50,40 +83,39 @@ mod tests {
                 0b0101_0101,
                 0b0111_1111,
                 0b1111_1111,
-            ],
-            10.0,
+            ]),
         );
-        assert_eq!(chunk.get_byte_at(0), 0b0000_0001);
-        assert_eq!(chunk.get_byte_at(7), 0b1111_1111);
-        assert_eq!(chunk.get_byte_at(3), 0b0001_0111);
+        assert_eq!(byte(chunk.get_byte_at(0)), 0b0000_0001);
+        assert_eq!(byte(chunk.get_byte_at(7)), 0b1111_1111);
+        assert_eq!(byte(chunk.get_byte_at(3)), 0b0001_0111);
     }
     #[test]
     fn set_bit() {
-        let mut chunk = Chunk::new([0, 0], 10.0)
+        let mut chunk = Chunk::new([0, 0])
             .set_bit((0, 0), true)
             .set_bit((1, 0), true)
             .set_bit((0, 1), true)
             .set_bit((2, 2), true);
-        assert_eq!(chunk.get_byte_at(0), 0b1100_0000);
-        assert_eq!(chunk.get_byte_at(1), 0b1000_0000);
-        assert_eq!(chunk.get_byte_at(2), 0b0010_0000);
+        assert_eq!(byte(chunk.get_byte_at(0)), 0b1100_0000);
+        assert_eq!(byte(chunk.get_byte_at(1)), 0b1000_0000);
+        assert_eq!(byte(chunk.get_byte_at(2)), 0b0010_0000);
         chunk = chunk
             .set_bit((0, 0), false)
             .set_bit((2, 0), false)
             .set_bit((2, 1), true);
-        assert_eq!(chunk.get_byte_at(0), 0b0100_0000);
-        assert_eq!(chunk.get_byte_at(1), 0b1010_0000);
+        assert_eq!(byte(chunk.get_byte_at(0)), 0b0100_0000);
+        assert_eq!(byte(chunk.get_byte_at(1)), 0b1010_0000);
         chunk = chunk.set_bit((0, 0), false).set_bit((1, 0), true);
-        assert_eq!(chunk.get_byte_at(0), 0b0100_0000);
+        assert_eq!(byte(chunk.get_byte_at(0)), 0b0100_0000);
         chunk.toggle_bit((0, 0));
         chunk.toggle_bit((1, 0));
-        assert_eq!(chunk.get_byte_at(0), 0b1000_0000);
+        assert_eq!(byte(chunk.get_byte_at(0)), 0b1000_0000);
     }
     #[test]
     fn survive_simple() {
         let chunk = Chunk::from(
             [0, 0],
-            [
+            rows([
                 0b0000_0000,
                 0b0001_0111,
                 0b0000_0000,
@@ -92,8 +124,7 @@ mod tests {
                 0b0010_0000,
                 0b0000_0000,
                 0b0000_0000,
-            ],
-            10.0,
+            ]),
         );
         let edges = Edges {
             left: 0,
@@ -102,38 +133,633 @@ mod tests {
             bottom: 0,
             corners: 0,
         };
-        assert_eq!(chunk.survive((1, 1), &edges), false);
-        assert_eq!(chunk.survive((3, 1), &edges), false);
-        assert_eq!(chunk.survive((4, 1), &edges), false);
-        assert_eq!(chunk.survive((5, 1), &edges), false);
-        assert_eq!(chunk.survive((6, 1), &edges), true);
-        assert_eq!(chunk.survive((6, 2), &edges), true);
-        assert_eq!(chunk.survive((2, 4), &edges), false);
-        assert_eq!(chunk.survive((3, 4), &edges), true);
-        assert_eq!(chunk.survive((2, 5), &edges), true);
-        assert_eq!(chunk.survive((3, 5), &edges), true);
+        assert!(!chunk.survive((1, 1), &edges, &Rule::default()));
+        assert!(!chunk.survive((3, 1), &edges, &Rule::default()));
+        assert!(!chunk.survive((4, 1), &edges, &Rule::default()));
+        assert!(!chunk.survive((5, 1), &edges, &Rule::default()));
+        assert!(chunk.survive((6, 1), &edges, &Rule::default()));
+        assert!(chunk.survive((6, 2), &edges, &Rule::default()));
+        assert!(!chunk.survive((2, 4), &edges, &Rule::default()));
+        assert!(chunk.survive((3, 4), &edges, &Rule::default()));
+        assert!(chunk.survive((2, 5), &edges, &Rule::default()));
+        assert!(chunk.survive((3, 5), &edges, &Rule::default()));
     }
     #[test]
     fn survive_neighbor() {}
     #[test]
+    fn rule_parse() {
+        let conway: Rule = "B3/S23".parse().unwrap();
+        assert_eq!(conway, Rule::default());
+        assert_eq!("23/3".parse::<Rule>().unwrap(), conway);
+        assert_eq!("s23/b3".parse::<Rule>().unwrap(), conway);
+        assert_eq!("B3S23".parse::<Rule>().unwrap(), conway);
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(highlife, Rule::new(&[3, 6], &[2, 3]));
+        assert_eq!(highlife.to_string(), "B36/S23");
+        let seeds: Rule = "B2/S".parse().unwrap();
+        assert_eq!(seeds.survival, 0);
+        assert_eq!("/2".parse::<Rule>().unwrap(), seeds);
+        let day_night: Rule = "B3678/S34678".parse().unwrap();
+        assert_eq!(day_night.to_string(), "B3678/S34678");
+    }
+    #[test]
+    fn rule_parse_errors() {
+        assert_eq!("".parse::<Rule>(), Err(ParseRuleError::Empty));
+        assert_eq!("B3".parse::<Rule>(), Err(ParseRuleError::MissingSeparator));
+        assert_eq!("23".parse::<Rule>(), Err(ParseRuleError::MissingSeparator));
+        assert_eq!(
+            "B39/S23".parse::<Rule>(),
+            Err(ParseRuleError::InvalidCount('9'))
+        );
+        assert_eq!(
+            "B33/S23".parse::<Rule>(),
+            Err(ParseRuleError::DuplicateCount('3'))
+        );
+        assert_eq!("B03/S23".parse::<Rule>(), Err(ParseRuleError::BirthOnZero));
+        assert!("X3/S23".parse::<Rule>().is_err());
+        assert!("1/2/3/4".parse::<Rule>().is_err());
+        assert_eq!(
+            "B2/S/C1".parse::<Rule>(),
+            Err(ParseRuleError::InvalidStates("1".to_string()))
+        );
+        assert!("/2/256".parse::<Rule>().is_err());
+        assert!("B2/S/C3/C4".parse::<Rule>().is_err());
+    }
+    #[test]
+    fn hensel_letters() {
+        let letters = rule::hensel_letters();
+        let class = |n: u32, letter: char| {
+            (0..=255u8)
+                .filter(|&c| c.count_ones() == n && letters[c as usize] == letter as u8)
+                .count()
+        };
+        let sizes: Vec<usize> = "ceaikn".chars().map(|l| class(2, l)).collect();
+        assert_eq!(sizes, vec![4, 4, 8, 2, 8, 2]);
+        assert_eq!(
+            "ceaiknjqrtwyz".chars().map(|l| class(4, l)).sum::<usize>(),
+            70
+        );
+        assert!((1..255).all(|c| letters[c] != 0));
+        // neighbours are NW, N, NE, W, E, SW, S, SE from bit 0
+        assert_eq!(letters[0b0100_0010] as char, 'i');
+        assert_eq!(letters[0b1000_0001] as char, 'n');
+        assert_eq!(letters[0b0000_0011] as char, 'a');
+        assert_eq!(letters[!0b0000_0100u8 as usize] as char, 'c');
+    }
+    #[test]
+    fn isotropic_parse() {
+        let just_friends: Rule = "B2-a/S12".parse().unwrap();
+        assert!(!just_friends.is_totalistic());
+        assert_eq!(just_friends.to_string(), "B2-a/S12");
+        assert_eq!("b2ceikn/s12".parse(), Ok(just_friends));
+        let tlife: Rule = "B3/S2-i34q".parse().unwrap();
+        assert_eq!(tlife.to_string(), "B3/S2-i34q");
+        assert_eq!(tlife.mixed(), (0, 1 << 2 | 1 << 4));
+        assert_eq!("B3/S2ceakn34q".parse(), Ok(tlife));
+        assert_eq!("B3cekainyqjr/S23".parse(), Ok(Rule::default()));
+        assert_eq!(
+            "B2ce/S/C3".parse::<Rule>().unwrap().to_string(),
+            "B2ce/S/C3"
+        );
+        assert_eq!(
+            "B1k/S".parse::<Rule>(),
+            Err(ParseRuleError::InvalidLetter('k', 1))
+        );
+        assert_eq!(
+            "B2-/S".parse::<Rule>(),
+            Err(ParseRuleError::InvalidLetter('-', 2))
+        );
+        assert_eq!(
+            "B2aa/S".parse::<Rule>(),
+            Err(ParseRuleError::InvalidLetter('a', 2))
+        );
+        assert!("B8c/S".parse::<Rule>().is_err());
+    }
+    #[test]
+    fn isotropic_step() {
+        let step = |rule: &str, cells: &[(i32, i32)], generations: u64| {
+            let mut game = Game::new(false);
+            game.set_rule(rule.parse().unwrap());
+            for &(x, y) in cells {
+                game.universe.set_cell(x, y, true);
+            }
+            game.step(generations);
+            game.universe.cells()
+        };
+        // A domino is a still life in Just Friends, where the cells beside it
+        // see a 2a configuration, but explodes under plain B2/S12.
+        let domino = [(0, 0), (0, 1)];
+        assert_eq!(step("B2-a/S12", &domino, 50), domino.to_vec());
+        assert_ne!(step("B2/S12", &domino, 1), domino.to_vec());
+        // tlife's 2i exception kills a blinker's middle cell
+        let blinker = [(5, 4), (5, 5), (5, 6)];
+        assert_eq!(step("B3/S23", &blinker, 2), blinker.to_vec());
+        assert_eq!(step("B3/S2-i34q", &blinker, 1), vec![(4, 5), (6, 5)]);
+        assert!(step("B3/S2-i34q", &blinker, 2).is_empty());
+        // and the glider still flies
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let moved = Pattern::from_cells(glider.iter().map(|&(x, y)| (x + 1, y + 1)).collect());
+        assert_eq!(step("B3/S2-i34q", &glider, 4), moved.cells);
+    }
+    #[test]
+    fn neighbourhood_parse() {
+        let hex: Rule = "B2/S34H".parse().unwrap();
+        assert_eq!(hex.neighbourhood, rule::Neighbourhood::Hexagonal);
+        assert_eq!(hex.to_string(), "B2/S34H");
+        assert_eq!("34/2h".parse(), Ok(hex));
+        let von_neumann: Rule = "B13/S012V".parse().unwrap();
+        assert_eq!(von_neumann.to_string(), "B13/S012V");
+        assert!(von_neumann.born_in(0b0000_0010));
+        assert!(!von_neumann.born_in(0b0000_0001));
+        assert_eq!(
+            "B5/S4V".parse::<Rule>(),
+            Err(ParseRuleError::InvalidCount('5'))
+        );
+        assert_eq!(
+            "B2a/S1V".parse::<Rule>(),
+            Err(ParseRuleError::UnsupportedNeighbourhood)
+        );
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(bosco.radius(), 5);
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        let diamond: Rule = "r3,c4,s2..5,b3..4,nn".parse().unwrap();
+        assert_eq!(diamond.to_string(), "R3,C4,M0,S2..5,B3..4,NN");
+        assert!(diamond.is_generations());
+        assert!("R11,C0,S1..2,B3..3,NM".parse::<Rule>().is_err());
+        assert!("R2,C0,S3..1,B1..2,NM".parse::<Rule>().is_err());
+        assert!("R2,C0,S1..2,NM".parse::<Rule>().is_err());
+        assert_eq!(
+            "R2,C0,S1..2,B0..2,NM".parse::<Rule>(),
+            Err(ParseRuleError::BirthOnZero)
+        );
+        let pattern = rle::read("x = 1, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\no!").unwrap();
+        assert_eq!(pattern.rule, Some(bosco));
+    }
+    // Larger than Life against a direct count over every cell in range, on a
+    // soup straddling chunk borders.
+    #[test]
+    fn larger_than_life() {
+        let n = SIZE as i32;
+        for rule in [
+            "R3,C0,M1,S8..20,B9..14,NM",
+            "R2,C0,M0,S3..6,B4..5,NN",
+            "R4,C3,M0,S10..30,B12..20,NM",
+        ]
+        .iter()
+        {
+            let rule: Rule = rule.parse().unwrap();
+            let range = rule.range.unwrap();
+            let r = range.radius as i32;
+            let mut universe = Universe::new();
+            universe.set_rule(rule);
+            let mut seed: u32 = 99;
+            let mut grid: HashMap<(i32, i32), u8> = HashMap::new();
+            for y in n - 8..n + 8 {
+                for x in -8..8 {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+                    if seed % 2 == 0 {
+                        universe.set_cell(x, y, true);
+                        grid.insert((x, y), 1);
+                    }
+                }
+            }
+            for _ in 0..8 {
+                let mut next = HashMap::new();
+                for y in n - 40..n + 40 {
+                    for x in -40..40 {
+                        let mut count = 0;
+                        for dy in -r..=r {
+                            for dx in -r..=r {
+                                let inside = rule.neighbourhood == rule::Neighbourhood::Moore
+                                    || dx.abs() + dy.abs() <= r;
+                                let counted = (dx, dy) != (0, 0) || range.middle;
+                                if inside && counted && grid.get(&(x + dx, y + dy)) == Some(&1) {
+                                    count += 1;
+                                }
+                            }
+                        }
+                        let state = match grid.get(&(x, y)).copied().unwrap_or(0) {
+                            0 if range.born(count) => 1,
+                            0 => 0,
+                            1 if range.survives(count) => 1,
+                            s if s + 1 < rule.states => s + 1,
+                            _ => 0,
+                        };
+                        if state != 0 {
+                            next.insert((x, y), state);
+                        }
+                    }
+                }
+                grid = next;
+                universe.iterate();
+                for y in n - 40..n + 40 {
+                    for x in -40..40 {
+                        let state = grid.get(&(x, y)).copied().unwrap_or(0);
+                        assert_eq!(universe.get_state(x, y), state, "{} at {}, {}", rule, x, y);
+                    }
+                }
+            }
+            assert_eq!(universe.population(), grid.len());
+        }
+        // radius 1 without the middle is Life
+        let mut life = Game::new(false);
+        let mut range = Game::new(false);
+        range.set_rule("R1,C0,M0,S2..3,B3..3,NM".parse().unwrap());
+        for game in [&mut life, &mut range].iter_mut() {
+            game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (n - 2, -1))
+                .unwrap();
+            game.step(30);
+        }
+        assert_eq!(range.universe.cells(), life.universe.cells());
+    }
+    #[test]
+    fn colored_rules() {
+        assert_eq!("Immigration".parse(), Ok(Rule::IMMIGRATION));
+        assert_eq!("quadlife".parse(), Ok(Rule::QUADLIFE));
+        assert_eq!(Rule::QUADLIFE.to_string(), "QuadLife");
+        assert_eq!(Rule::QUADLIFE.state_bits(), 2);
+        assert_eq!(Rule::IMMIGRATION.birth_color(&[2, 1]), 0);
+        assert_eq!(Rule::IMMIGRATION.birth_color(&[0, 3]), 1);
+        assert_eq!(Rule::QUADLIFE.birth_color(&[1, 1, 0, 1]), 2);
+        assert_eq!(Rule::QUADLIFE.birth_color(&[0, 1, 2, 0]), 2);
+
+        let pattern = rle::read("x = 3, y = 1, rule = QuadLife\nABC!").unwrap();
+        assert_eq!(pattern.cells, vec![(0, 0)]);
+        assert_eq!(pattern.states, vec![(1, 0, 2), (2, 0, 3)]);
+        let mut game = Game::new(false);
+        game.load_pattern(pattern, (0, 0));
+        assert_eq!(game.universe.cells(), vec![(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(game.to_rle(), "x = 3, y = 1, rule = QuadLife\nABC!\n");
+        let copy = save::read(&save::write(&game.save_data())).unwrap();
+        let mut loaded = Game::new(false);
+        loaded.load_save_data(copy);
+        assert_eq!(loaded.universe.states(), game.universe.states());
+
+        // clicks paint the chosen colour, and clear a cell already painted
+        let mut game = Game::new(false);
+        game.set_rule(Rule::IMMIGRATION);
+        game.next_color();
+        assert_eq!(game.color, 2);
+        game.click(game.cell_center((3, 4)));
+        assert_eq!(game.universe.get_state(3, 4), 2);
+        game.click(game.cell_center((3, 4)));
+        assert_eq!(game.universe.get_state(3, 4), 0);
+        game.next_color();
+        assert_eq!(game.color, 1);
+    }
+    // Births take their parents' majority colour, or QuadLife's missing
+    // one, including across chunk borders, while the live cells follow Life.
+    #[test]
+    fn colored_step() {
+        let n = SIZE as i32;
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 1, rule = Immigration\nBAB!", (n - 2, 0))
+            .unwrap();
+        game.iterate();
+        assert_eq!(
+            game.universe.cells(),
+            vec![(n - 1, -1), (n - 1, 0), (n - 1, 1)]
+        );
+        let states: Vec<u8> = (-1..2).map(|y| game.universe.get_state(n - 1, y)).collect();
+        assert_eq!(states, vec![2, 1, 2]);
+
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 1, rule = QuadLife\nABC!", (n - 2, 0))
+            .unwrap();
+        game.iterate();
+        let states: Vec<u8> = (-1..2).map(|y| game.universe.get_state(n - 1, y)).collect();
+        assert_eq!(states, vec![4, 2, 4]);
+        game.iterate();
+        let states: Vec<u8> = (n - 2..n + 1)
+            .map(|x| game.universe.get_state(x, 0))
+            .collect();
+        assert_eq!(states, vec![4, 2, 4]);
+
+        let mut life = Game::new(false);
+        let mut quad = Game::new(false);
+        quad.set_rule(Rule::QUADLIFE);
+        for (i, game) in [&mut life, &mut quad].iter_mut().enumerate() {
+            game.load_rle("x = 3, y = 3\nb2o$2o$bo!", (n - 2, -1))
+                .unwrap();
+            if i == 1 {
+                game.universe.set_state(n - 1, -1, 3);
+                game.universe.set_state(n - 2, 0, 4);
+            }
+            game.step(40);
+        }
+        assert_eq!(quad.universe.cells(), life.universe.cells());
+        assert_eq!(quad.universe.population(), life.universe.population());
+    }
+    #[test]
+    fn rule_table_parse() {
+        use table::{RuleTable, TableError};
+        let wireworld = RuleTable::wireworld();
+        assert_eq!(wireworld.states, 4);
+        assert_eq!(wireworld.color(1), Some([0, 128, 255]));
+        assert_eq!(wireworld.next([3, 0, 0, 1, 0, 0, 0, 0, 0]), 1);
+        assert_eq!(wireworld.next([3, 1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(wireworld.to_rule(), None);
+
+        let table = |body: &str| RuleTable::parse(&format!("@RULE Test\n@TABLE\n{}", body));
+        // births next to a single orthogonal neighbour, given for N only
+        let turns =
+            table("n_states:2\nneighborhood:vonNeumann\nsymmetries:rotate4\n010001").unwrap();
+        assert_eq!(turns.next([0, 0, 0, 1, 0, 0, 0, 0, 0]), 1);
+        assert_eq!(turns.next([0, 1, 1, 0, 0, 0, 0, 0, 0]), 0);
+        assert_eq!(turns.to_rule(), "B1/S01234V".parse().ok());
+        let north = table("n_states:2\n0,1,0,0,0,0,0,0,0,1").unwrap();
+        assert_eq!(north.to_rule(), None);
+        let mirror =
+            table("n_states:3\nsymmetries:reflect_horizontal\n0,0,2,0,0,0,0,0,0,2").unwrap();
+        assert_eq!(mirror.next([0, 0, 0, 0, 0, 0, 0, 0, 2]), 2);
+        assert_eq!(mirror.next([0, 0, 0, 2, 0, 0, 0, 0, 0]), 0);
+
+        // Life written out as a table runs as the rule itself
+        let life = table(
+            "n_states:2\nsymmetries:permute\nvar a={0,1}\nvar b={a}\nvar c={a}\nvar d={a}
+var e={a}\nvar f={a}\nvar g={a}\nvar h={a}\n0,1,1,1,0,0,0,0,0,1\n1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1\n1,a,b,c,d,e,f,g,h,0",
+        )
+        .unwrap();
+        assert_eq!(life.to_rule(), Some(Rule::default()));
+        let mut universe = Universe::new();
+        universe.set_rule(Rule::IMMIGRATION);
+        universe.set_table(std::sync::Arc::new(life));
+        assert_eq!(universe.rule, Rule::default());
+        assert!(universe.table.is_none());
+
+        assert_eq!(
+            RuleTable::parse("@RULE Test\nn_states:2"),
+            Err(TableError::Missing("@TABLE section"))
+        );
+        assert_eq!(
+            table("n_states:2\nneighborhood:triangular"),
+            Err(TableError::UnsupportedNeighbourhood(
+                "triangular".to_string()
+            ))
+        );
+        assert!(matches!(
+            table("n_states:2\nneighborhood:hexagonal\nsymmetries:rotate4"),
+            Err(TableError::UnsupportedSymmetry(_))
+        ));
+        assert_eq!(
+            table("n_states:2\nvar a={0,1}\n0,a,0,0,0,0,0,0,0,b"),
+            Err(TableError::InvalidTransition(
+                5,
+                "0,a,0,0,0,0,0,0,0,b".to_string()
+            ))
+        );
+        assert!(matches!(
+            table("n_states:2\n0,1,2,0,0,0,0,0,0,1"),
+            Err(TableError::InvalidTransition(4, _))
+        ));
+        assert!(matches!(
+            table("n_states:2\nvar a={0,5}"),
+            Err(TableError::InvalidVariable(4, _))
+        ));
+    }
+    // Wireworld from its table against the rule applied directly, on a soup
+    // straddling chunk borders, plus the files it is kept in.
+    #[test]
+    fn wireworld() {
+        let n = SIZE as i32;
+        let mut game = Game::new(false);
+        game.load_rle("x = 7, y = 1, rule = WireWorld\nBA5C!", (n - 3, 0))
+            .unwrap();
+        assert!(game.universe.table.is_some());
+        game.step(3);
+        let states: Vec<u8> = (n - 3..n + 4)
+            .map(|x| game.universe.get_state(x, 0))
+            .collect();
+        assert_eq!(states, vec![3, 3, 3, 2, 1, 3, 3]);
+        assert_eq!(game.to_rle(), "x = 7, y = 1, rule = WireWorld\n3CBA2C!\n");
+
+        let mut universe = Universe::new();
+        universe.set_table(std::sync::Arc::new(table::RuleTable::wireworld()));
+        let mut seed: u32 = 7;
+        let mut grid: HashMap<(i32, i32), u8> = HashMap::new();
+        for y in n - 10..n + 10 {
+            for x in -10..10 {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let state = [0, 0, 1, 2, 3, 3, 3, 3][(seed % 8) as usize];
+                if state != 0 {
+                    universe.set_state(x, y, state);
+                    grid.insert((x, y), state);
+                }
+            }
+        }
+        for _ in 0..12 {
+            let mut next = HashMap::new();
+            for (&(x, y), &state) in grid.iter() {
+                let heads = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+                    .filter(|&cell| grid.get(&cell) == Some(&1))
+                    .count();
+                let state = match state {
+                    1 => 2,
+                    2 => 3,
+                    _ if heads == 1 || heads == 2 => 1,
+                    _ => 3,
+                };
+                next.insert((x, y), state);
+            }
+            grid = next;
+            universe.iterate();
+        }
+        for y in n - 12..n + 12 {
+            for x in -12..12 {
+                let state = grid.get(&(x, y)).copied().unwrap_or(0);
+                assert_eq!(universe.get_state(x, y), state, "at {}, {}", x, y);
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("life-rust-table-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = table::RuleTable::wireworld()
+            .source()
+            .replace("WireWorld", "Wires");
+        std::fs::write(dir.join("Wires.rule"), source).unwrap();
+        let mut game = Game::new(false);
+        game.open(&dir.join("Wires.rule")).unwrap();
+        assert_eq!(game.universe.table.as_ref().unwrap().name, "Wires");
+        game.load_rle("x = 3, y = 1, rule = Wires\nBAC!", (0, 0))
+            .unwrap();
+        game.save(&dir.join("wires.cgl")).unwrap();
+        let mut copy = Game::new(false);
+        copy.open(&dir.join("wires.cgl")).unwrap();
+        assert_eq!(copy.universe.table, game.universe.table);
+        copy.iterate();
+        assert_eq!(copy.universe.get_state(2, 0), 1);
+        assert!(rle::read("x = 3, y = 1, rule = Wires\nBAC!").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn generations_parse() {
+        let brians_brain: Rule = "/2/3".parse().unwrap();
+        assert_eq!(brians_brain, Rule::generations(&[2], &[], 3));
+        assert_eq!(brians_brain.to_string(), "B2/S/C3");
+        assert_eq!("B2/S/C3".parse(), Ok(brians_brain));
+        let star_wars: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars, Rule::generations(&[2], &[3, 4, 5], 4));
+        assert_eq!(star_wars.state_bits(), 3);
+        assert_eq!("B3/S23/C2".parse(), Ok(Rule::default()));
+        assert!(!Rule::default().is_generations());
+    }
+    // Steps Generations rules over a soup spread across several chunks and
+    // compares every state with a direct per-cell implementation.
+    #[test]
+    fn generations_step() {
+        let n = SIZE as i32;
+        for rule in ["/2/3", "345/2/4", "B35/S23/C8"].iter() {
+            let rule: Rule = rule.parse().unwrap();
+            let mut universe = Universe::new();
+            universe.set_rule(rule);
+            let mut seed: u32 = 7;
+            let mut random = || {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed
+            };
+            let mut grid: HashMap<(i32, i32), u8> = HashMap::new();
+            for y in n - 12..n + 12 {
+                for x in -12..12 {
+                    let state = (random() % rule.states as u32) as u8;
+                    if state != 0 {
+                        universe.set_state(x, y, state);
+                        grid.insert((x, y), state);
+                    }
+                }
+            }
+            for _ in 0..20 {
+                let mut next = HashMap::new();
+                for y in n - 40..n + 40 {
+                    for x in -40..40 {
+                        let state = grid.get(&(x, y)).copied().unwrap_or(0);
+                        let mut count = 0;
+                        for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                            if (dx, dy) != (0, 0) && grid.get(&(x + dx, y + dy)) == Some(&1) {
+                                count += 1;
+                            }
+                        }
+                        let state = match state {
+                            0 if rule.born(count) => 1,
+                            0 => 0,
+                            1 if rule.survives(count) => 1,
+                            s if s + 1 < rule.states => s + 1,
+                            _ => 0,
+                        };
+                        if state != 0 {
+                            next.insert((x, y), state);
+                        }
+                    }
+                }
+                grid = next;
+                universe.iterate();
+                for y in n - 40..n + 40 {
+                    for x in -40..40 {
+                        let state = grid.get(&(x, y)).copied().unwrap_or(0);
+                        assert_eq!(universe.get_state(x, y), state, "{} at {}, {}", rule, x, y);
+                    }
+                }
+            }
+            assert_eq!(universe.population(), grid.len());
+        }
+    }
+    #[test]
+    fn generations_files() {
+        let pattern = rle::read("x = 4, y = 2, rule = /2/30\n.AB$pA2.C!").unwrap();
+        assert_eq!(pattern.rule, Some(Rule::generations(&[2], &[], 30)));
+        assert_eq!(pattern.cells, vec![(1, 0)]);
+        assert_eq!(pattern.states, vec![(2, 0, 2), (0, 1, 25), (3, 1, 3)]);
+        assert_eq!(
+            rle::write(&pattern),
+            "x = 4, y = 2, rule = B2/S/C30\n.AB$pA2.C!\n"
+        );
+        // a three-state rule has no state 25
+        assert_eq!(
+            rle::read("x = 4, y = 2, rule = /2/3\n.AB$pA2.C!"),
+            Err(rle::RleError::InvalidState(25, 2))
+        );
+        assert_eq!(
+            rle::read("#r /2/3\nx = 1, y = 3\nB$A$\nC!"),
+            Err(rle::RleError::InvalidState(3, 4))
+        );
+        assert!(rle::read("x = 1, y = 1\npo!").is_err());
+
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 2, rule = /2/3\n3A$.B!", (SIZE as i32 - 2, 0))
+            .unwrap();
+        game.iterate();
+        let bytes = save::write(&game.save_data());
+        let mut copy = Game::new(false);
+        copy.load_save_data(save::read(&bytes).unwrap());
+        assert_eq!(copy.universe.rule, game.universe.rule);
+        assert_eq!(copy.universe.states(), game.universe.states());
+        assert_eq!(copy.universe.cells(), game.universe.cells());
+        assert!(!game.universe.states().is_empty());
+    }
+    #[test]
+    fn survive_rule() {
+        let chunk = Chunk::from(
+            [0, 0],
+            rows([
+                0b0000_0000,
+                0b0111_0000,
+                0b0000_0000,
+                0b0000_0000,
+                0b0000_0000,
+                0b0001_1100,
+                0b0001_0100,
+                0b0000_1000,
+            ]),
+        );
+        let edges = Edges {
+            left: 0,
+            right: 0,
+            top: 0,
+            bottom: 0,
+            corners: 0,
+        };
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        let life_without_death: Rule = "B3/S012345678".parse().unwrap();
+        // a dead cell with six neighbours is only born under HighLife
+        assert!(!chunk.survive((4, 6), &edges, &Rule::default()));
+        assert!(chunk.survive((4, 6), &edges, &highlife));
+        // the ends of a blinker die under Conway but not under Life without Death
+        assert!(!chunk.survive((1, 1), &edges, &Rule::default()));
+        assert!(chunk.survive((1, 1), &edges, &life_without_death));
+    }
+    #[test]
     fn game() {
-        let mut game = Game::from([0b0111_1111; 8]);
-        assert_eq!(game.map.get(&[0, 0]).unwrap().get_byte_at(0), 0b0111_1111);
-        game.insert_chunk([1, 0], Chunk::from([1, 0], [0b1011_1111; 8], 10.0));
-        assert_eq!(game.map.get(&[1, 0]).unwrap().get_byte_at(0), 0b1011_1111);
-        game.insert_chunk([0, 0], Chunk::new([0, 0], 10.0));
-        assert_eq!(game.map.get(&[0, 0]).unwrap().get_byte_at(0), 0b0000_0000);
+        let mut game = Universe::from(rows([0b0111_1111; 8]));
+        assert_eq!(
+            byte(game.map.get(&[0, 0]).unwrap().get_byte_at(0)),
+            0b0111_1111
+        );
+        game.insert_chunk([1, 0], Chunk::from([1, 0], rows([0b1011_1111; 8])));
+        assert_eq!(
+            byte(game.map.get(&[1, 0]).unwrap().get_byte_at(0)),
+            0b1011_1111
+        );
+        game.insert_chunk([0, 0], Chunk::new([0, 0]));
         assert_eq!(
-            game.chunks()[1].get_byte_at(0) == game.map.get(&[0, 0]).unwrap().get_byte_at(0)
-                || game.chunks()[0].get_byte_at(0) == game.map.get(&[0, 0]).unwrap().get_byte_at(0),
-            true
+            byte(game.map.get(&[0, 0]).unwrap().get_byte_at(0)),
+            0b0000_0000
         );
+        let zero = game.map.get(&[0, 0]).unwrap().get_byte_at(0);
+        let chunks = game.chunks();
+        assert!(chunks[1].get_byte_at(0) == zero || chunks[0].get_byte_at(0) == zero);
     }
     #[test]
     fn chunk_iteration() {
         let mut c = Chunk::from(
             [0, 0],
-            [
+            rows([
                 0b0000_0000,
                 0b0010_0000,
                 0b0111_0000,
@@ -142,12 +768,11 @@ mod tests {
                 0b0000_0000,
                 0b0000_0110,
                 0b0000_0110,
-            ],
-            10.0,
+            ]),
         );
         let c2 = Chunk::from(
             [0, 0],
-            [
+            rows([
                 0b0000_0000,
                 0b0111_0000,
                 0b0101_0000,
@@ -156,8 +781,7 @@ mod tests {
                 0b0000_0000,
                 0b0000_0110,
                 0b0000_0110,
-            ],
-            10.0,
+            ]),
         );
         let edges = Edges {
             left: 0,
@@ -166,101 +790,1372 @@ mod tests {
             bottom: 0,
             corners: 0,
         };
-        c.iterate(&edges);
-        assert_eq!(chunks_eq(&c, &c2), true);
-        c.iterate(&edges);
-        assert_eq!(chunks_eq(&c, &c2), false);
+        c.iterate(&edges, &Rule::default());
+        assert!(chunks_eq(&c, &c2));
+        c.iterate(&edges, &Rule::default());
+        assert!(!chunks_eq(&c, &c2));
     }
     #[test]
     fn edges() {
-        let mut game = Game::from([
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0001,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-        ]);
-        game.insert_chunk(
-            [1, 0],
-            Chunk::from(
-                [1, 0],
-                [
-                    0b0000_0000,
-                    0b0000_0000,
-                    0b1100_0000,
-                    0b0000_0000,
-                    0b0000_0000,
-                    0b0000_0000,
-                    0b0000_0000,
-                    0b0000_0000,
-                ],
-                10.0,
-            ),
-        );
+        let n = SIZE as i32;
+        let last = SIZE as i8 - 1;
+        let mut game = Universe::new();
+        game.set_cell(n - 1, 2, true);
+        game.set_cell(n, 2, true);
+        game.set_cell(n + 1, 2, true);
+        assert_eq!(game.edges([1, 0], 0).left, 1 << (SIZE - 3));
+        assert_eq!(game.edges([0, 0], 0).right, 1 << (SIZE - 3));
         game.iterate();
         let c = game.map.get(&[1, 0]).unwrap();
         let c2 = game.map.get(&[0, 0]).unwrap();
-        assert_eq!(c.get_bit_at_point((0, 1)), true);
-        assert_eq!(c.get_bit_at_point((0, 2)), true);
-        assert_eq!(c.get_bit_at_point((0, 3)), true);
-        assert_eq!(c2.get_bit_at_point((7, 2)), false);
+        assert!(c.get_bit_at_point((0, 1)));
+        assert!(c.get_bit_at_point((0, 2)));
+        assert!(c.get_bit_at_point((0, 3)));
+        assert!(!c2.get_bit_at_point((last, 2)));
         game.iterate();
         let c = game.map.get(&[1, 0]).unwrap();
         let c2 = game.map.get(&[0, 0]).unwrap();
-        assert_eq!(c.get_bit_at_point((0, 1)), false);
-        assert_eq!(c.get_bit_at_point((0, 2)), true);
-        assert_eq!(c.get_bit_at_point((0, 3)), false);
-        assert_eq!(c.get_bit_at_point((1, 2)), true);
-        assert_eq!(c2.get_bit_at_point((7, 2)), true);
+        assert!(!c.get_bit_at_point((0, 1)));
+        assert!(c.get_bit_at_point((0, 2)));
+        assert!(!c.get_bit_at_point((0, 3)));
+        assert!(c.get_bit_at_point((1, 2)));
+        assert!(c2.get_bit_at_point((last, 2)));
     }
     #[test]
     fn corners() {
-        let mut game = Game::from([
-            0b1000_0001,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b1000_0001,
-        ]);
-        game.insert_chunk([-1, -1], Chunk::new([-1, -1], 10.0).set_bit((7, 0), true));
-        game.insert_chunk([1, -1], Chunk::new([1, -1], 10.0).set_bit((0, 0), true));
-        game.insert_chunk([-1, 1], Chunk::new([-1, 1], 10.0).set_bit((7, 7), true));
-        game.insert_chunk([1, 1], Chunk::new([1, 1], 10.0).set_bit((0, 7), true));
+        let last = SIZE as u8 - 1;
+        let corner: Row = 1 << last | 1;
+        let mut chunk = [0; SIZE];
+        chunk[0] = corner;
+        chunk[SIZE - 1] = corner;
+        let mut game = Universe::from(chunk);
+        game.insert_chunk([-1, -1], Chunk::new([-1, -1]).set_bit((last, 0), true));
+        game.insert_chunk([1, -1], Chunk::new([1, -1]).set_bit((0, 0), true));
+        game.insert_chunk([-1, 1], Chunk::new([-1, 1]).set_bit((last, last), true));
+        game.insert_chunk([1, 1], Chunk::new([1, 1]).set_bit((0, last), true));
         let corners = game.corners([0, 0]);
         assert_eq!(corners, 0b1111_0000);
-        game.insert_chunk([-1, -1], Chunk::new([-1, -1], 10.0).set_bit((7, 0), false));
-        game.insert_chunk([1, -1], Chunk::new([1, -1], 10.0).set_bit((0, 0), true));
-        game.insert_chunk([-1, 1], Chunk::new([-1, 1], 10.0).set_bit((7, 7), true));
-        game.insert_chunk([1, 1], Chunk::new([1, 1], 10.0).set_bit((0, 7), false));
+        game.insert_chunk([-1, -1], Chunk::new([-1, -1]).set_bit((last, 0), false));
+        game.insert_chunk([1, -1], Chunk::new([1, -1]).set_bit((0, 0), true));
+        game.insert_chunk([-1, 1], Chunk::new([-1, 1]).set_bit((last, last), true));
+        game.insert_chunk([1, 1], Chunk::new([1, 1]).set_bit((0, last), false));
         let corners = game.corners([0, 0]);
         assert_eq!(corners, 0b0110_0000);
-        game.insert_chunk([-1, -1], Chunk::new([-1, -1], 10.0).set_bit((7, 0), true));
-        game.insert_chunk([1, -1], Chunk::new([1, -1], 10.0).set_bit((0, 0), false));
-        game.insert_chunk([-1, 1], Chunk::new([-1, 1], 10.0).set_bit((7, 7), true));
-        game.insert_chunk([1, 1], Chunk::new([1, 1], 10.0).set_bit((0, 7), false));
+        game.insert_chunk([-1, -1], Chunk::new([-1, -1]).set_bit((last, 0), true));
+        game.insert_chunk([1, -1], Chunk::new([1, -1]).set_bit((0, 0), false));
+        game.insert_chunk([-1, 1], Chunk::new([-1, 1]).set_bit((last, last), true));
+        game.insert_chunk([1, 1], Chunk::new([1, 1]).set_bit((0, last), false));
         let corners = game.corners([0, 0]);
         assert_eq!(corners, 0b1010_0000);
-        game.insert_chunk([-1, -1], Chunk::new([-1, -1], 10.0).set_bit((7, 0), false));
-        game.insert_chunk([1, -1], Chunk::new([1, -1], 10.0).set_bit((0, 0), false));
-        game.insert_chunk([-1, 1], Chunk::new([-1, 1], 10.0).set_bit((7, 7), false));
-        game.insert_chunk([1, 1], Chunk::new([1, 1], 10.0).set_bit((0, 7), false));
+        game.insert_chunk([-1, -1], Chunk::new([-1, -1]).set_bit((last, 0), false));
+        game.insert_chunk([1, -1], Chunk::new([1, -1]).set_bit((0, 0), false));
+        game.insert_chunk([-1, 1], Chunk::new([-1, 1]).set_bit((last, last), false));
+        game.insert_chunk([1, 1], Chunk::new([1, 1]).set_bit((0, last), false));
         let corners = game.corners([0, 0]);
         assert_eq!(corners, 0b0000_0000);
     }
-}
+    #[test]
+    fn universe_cells() {
+        let n = SIZE as i32;
+        let last = SIZE as i8 - 1;
+        let mut universe = Universe::new();
+        universe.set_cell(n - 1, 0, true);
+        universe.set_cell(n, -1, true);
+        universe.set_cell(-1, n, true);
+        assert!(universe
+            .map
+            .get(&[0, 0])
+            .unwrap()
+            .get_bit_at_point((last, 0)));
+        assert!(universe
+            .map
+            .get(&[1, 1])
+            .unwrap()
+            .get_bit_at_point((0, last)));
+        assert!(universe
+            .map
+            .get(&[-1, -1])
+            .unwrap()
+            .get_bit_at_point((last, 0)));
+        assert_eq!(universe.cells(), vec![(n, -1), (n - 1, 0), (-1, n)]);
+        assert_eq!(universe.population(), 3);
+        universe.set_cell(n - 1, 0, false);
+        assert!(!universe.get_cell(n - 1, 0));
+        assert_eq!(universe.population(), 2);
+    }
+    #[test]
+    fn glider_crosses_chunks() {
+        // starts just inside the bottom right corner of chunk [0, 0]
+        let n = SIZE as i32 - 8;
+        let mut universe = Universe::new();
+        for &(x, y) in &[(6, 5), (7, 6), (5, 7), (6, 7), (7, 7)] {
+            universe.set_cell(x + n, y + n, true);
+        }
+        for _ in 0..8 {
+            universe.iterate();
+        }
+        let moved: Vec<(i32, i32)> = [(8, 7), (9, 8), (7, 9), (8, 9), (9, 9)]
+            .iter()
+            .map(|&(x, y)| (x + n, y + n))
+            .collect();
+        assert_eq!(universe.cells(), moved);
+        assert_eq!(universe.generation, 8);
+    }
+    #[test]
+    fn game_click() {
+        // the screen origin is the centre of chunk [0, 0]
+        let h = SIZE as i32 / 2;
+        let mut game = Game::new(false);
+        game.click((0.0, 0.0));
+        game.click((-35.0, 35.0));
+        assert_eq!(game.universe.cells(), vec![(h - 4, h - 4), (h, h)]);
+        assert_eq!(game.cell_at(game.cell_center((4, 4))), (4, 4));
+        game.pos((5.0, -5.0));
+        game.zoom(true);
+        assert_eq!(game.cell_at(game.cell_center((-9, 13))), (-9, 13));
+        game.click(game.cell_center((h, h)));
+        assert_eq!(game.universe.cells(), vec![(h - 4, h - 4)]);
+    }
+    #[test]
+    fn undo_redo() {
+        let h = SIZE as i32 / 2;
+        let mut game = Game::new(false);
+        assert!(!game.undo());
+        game.click((0.0, 0.0));
+        game.click((-35.0, 35.0));
+        game.undo();
+        assert_eq!(game.universe.cells(), vec![(h, h)]);
+        game.redo();
+        assert_eq!(game.universe.cells(), vec![(h - 4, h - 4), (h, h)]);
+        assert!(!game.redo());
+        game.clear();
+        assert_eq!(game.universe.population(), 0);
+        game.undo();
+        assert_eq!(game.universe.population(), 2);
+        // a new edit forgets what was undone
+        game.undo();
+        game.click((0.0, 0.0));
+        assert!(!game.history.can_redo());
+        assert_eq!(game.universe.population(), 0);
 
-fn chunks_eq(c1: &Chunk, c2: &Chunk) -> bool {
-    let mut val = true;
-    for i in 0..8 {
-        if !(c1.get_byte_at(i) == c2.get_byte_at(i)) {
-            val = false;
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0)).unwrap();
+        let start = game.universe.cells();
+        game.checkpoint();
+        game.step(12);
+        let moved = game.universe.cells();
+        game.undo();
+        assert_eq!(game.universe.cells(), start);
+        assert_eq!(game.universe.generation, 0);
+        game.redo();
+        assert_eq!(game.universe.cells(), moved);
+        assert_eq!(game.universe.generation, 12);
+        // the load itself can be undone too
+        game.undo();
+        game.undo();
+        assert_eq!(game.universe.population(), 0);
+    }
+    #[test]
+    fn history_limit() {
+        let mut game = Game::new(false);
+        game.history.set_limit(3);
+        for x in 0..5 {
+            game.checkpoint();
+            game.universe.set_cell(x, 0, true);
+        }
+        let mut undone = 0;
+        while game.undo() {
+            undone += 1;
+        }
+        assert_eq!(undone, 3);
+        assert_eq!(game.universe.population(), 2);
+        game.history.set_limit(0);
+        game.click((0.0, 0.0));
+        assert!(!game.history.can_undo());
+        // snapshots are also limited by the chunks they hold
+        let mut game = Game::new(false);
+        game.history.set_budget(30);
+        for x in 0..5 {
+            game.checkpoint();
+            game.universe.set_cell(x * SIZE as i32 * 3, 0, true);
+        }
+        // the snapshots hold 1, 4, 8, 12 and 16 chunks, so the last two fit
+        let undo_all = |game: &mut Game| {
+            let mut undone = 0;
+            while game.undo() {
+                undone += 1;
+            }
+            undone
+        };
+        assert_eq!(undo_all(&mut game), 2);
+        assert_eq!(game.universe.population(), 3);
+        // but the latest edit is kept however large it is
+        assert!(game.redo() && game.redo());
+        game.history.set_budget(1);
+        assert_eq!(undo_all(&mut game), 1);
+    }
+    #[test]
+    fn step_back() {
+        let mut game = Game::new(false);
+        game.rewind.set_stride(1);
+        game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0)).unwrap();
+        let mut seen = vec![game.universe.cells()];
+        for _ in 0..5 {
+            game.iterate();
+            seen.push(game.universe.cells());
+        }
+        assert_eq!(game.rewind.generations(), vec![0, 1, 2, 3, 4]);
+        assert!(game.step_back(1));
+        assert_eq!(game.universe.generation, 4);
+        assert_eq!(game.universe.cells(), seen[4]);
+        assert!(game.rewind_to(1));
+        assert_eq!(game.universe.cells(), seen[1]);
+        // the present is kept so that it can be scrubbed back to
+        assert!(game.rewind_to(5));
+        assert_eq!(game.universe.cells(), seen[5]);
+        assert!(game.undo());
+        assert_eq!(game.universe.generation, 1);
+        assert!(!game.step_back(2));
+        // stepping on from an earlier generation replaces what followed it
+        game.iterate();
+        assert_eq!(game.rewind.generations(), vec![0, 1]);
+        assert_eq!(game.universe.cells(), seen[2]);
+
+        game.rewind.set_capacity(3);
+        game.step(4);
+        game.iterate();
+        assert_eq!(game.rewind.generations(), vec![1, 2, 6]);
+        // generations that aren't retained are stepped to from an earlier one
+        assert!(game.step_back(3));
+        assert_eq!(game.universe.generation, 4);
+        assert_eq!(game.universe.cells(), seen[4]);
+
+        // by default only every tenth generation is copied
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0)).unwrap();
+        let mut expected = Game::new(false);
+        expected
+            .load_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0))
+            .unwrap();
+        game.step(25);
+        assert_eq!(game.rewind.generations(), vec![0]);
+        for _ in 0..25 {
+            game.iterate();
+        }
+        assert_eq!(game.rewind.generations(), vec![0, 25, 35, 45]);
+        assert!(game.step_back(8));
+        assert_eq!(game.universe.generation, 42);
+        expected.step(42);
+        assert_eq!(game.universe.cells(), expected.universe.cells());
+        // and the copies are limited by the chunks they hold, keeping the
+        // newest
+        game.rewind.set_budget(1);
+        assert_eq!(game.rewind.generations(), vec![50]);
+    }
+    #[test]
+    fn selection() {
+        let n = SIZE as i32;
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (n - 2, n - 2))
+            .unwrap();
+        game.universe.set_cell(-5, -5, true);
+        assert_eq!(game.copy(), None);
+        game.select((n + 1, n + 1), (n - 1, n - 1));
+        assert_eq!(game.selection.unwrap().width(), 3);
+        let copied = game.copy().unwrap();
+        assert_eq!(copied, "x = 2, y = 2, rule = B3/S23\nbo$2o!\n");
+        assert_eq!(game.clipboard.as_deref(), Some(copied.as_str()));
+        game.cut();
+        assert_eq!(
+            game.universe.cells(),
+            vec![(-5, -5), (n - 1, n - 2), (n - 2, n)]
+        );
+        game.undo();
+        assert_eq!(game.universe.population(), 6);
+        game.clear_outside();
+        assert_eq!(game.universe.cells(), vec![(n, n - 1), (n - 1, n), (n, n)]);
+        game.undo();
+
+        // a paste follows the cursor until clicked
+        game.deselect();
+        game.paste(&copied).unwrap();
+        game.hover(game.cell_center((10, 20)));
+        assert_eq!(game.cursor, (10, 20));
+        game.click(game.cell_center((10, 20)));
+        assert!(game.pasting.is_none());
+        game.select((10, 20), (20, 30));
+        assert_eq!(
+            game.selected_pattern().unwrap().cells,
+            vec![(1, 0), (0, 1), (1, 1)]
+        );
+        game.undo();
+        assert_eq!(game.selected_pattern().unwrap().cells, vec![]);
+        assert!(game.paste("3o").is_err());
+    }
+    #[test]
+    fn transforms() {
+        use crate::pattern::Transform;
+        let glider = rle::read("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        // turned a quarter clockwise it heads south west
+        let south_west = vec![(0, 0), (0, 1), (2, 1), (0, 2), (1, 2)];
+        let mut turned = glider.clone();
+        turned.transform(Transform::Rotate90);
+        assert_eq!(turned.cells, south_west);
+        let mut flipped = glider.clone();
+        flipped.transform(Transform::FlipHorizontal);
+        flipped.transform(Transform::FlipVertical);
+        let mut half = glider.clone();
+        half.transform(Transform::Rotate180);
+        assert_eq!(flipped, half);
+        half.transform(Transform::Rotate90);
+        turned.transform(Transform::Rotate270);
+        half.transform(Transform::Rotate270);
+        assert_eq!(turned, glider);
+        assert_eq!(half.cells, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 2)]);
+
+        // a selection crossing chunk boundaries turns in place
+        let n = SIZE as i32;
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (n - 1, n - 1))
+            .unwrap();
+        let start = game.universe.cells();
+        game.select((n - 1, n - 1), (n + 2, n + 1));
+        assert!(game.transform(Transform::Rotate90));
+        let selection = game.selection.unwrap();
+        assert_eq!((selection.width(), selection.height()), (3, 4));
+        let moved: Vec<(i32, i32)> = south_west
+            .iter()
+            .map(|&(x, y)| (x + n - 1, y + n - 1))
+            .collect();
+        assert_eq!(game.universe.cells(), moved);
+        for _ in 0..3 {
+            game.transform(Transform::Rotate90);
+        }
+        assert_eq!(game.universe.cells(), start);
+        game.transform(Transform::FlipVertical);
+        game.undo();
+        assert_eq!(game.universe.cells(), start);
+
+        // as does a paste before it is placed
+        game.paste("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        game.transform(Transform::Rotate90);
+        assert_eq!(game.pasting.as_ref().unwrap().cells, south_west);
+    }
+    #[test]
+    fn drawing_tools() {
+        use crate::draw::{self, Tool};
+        assert_eq!(
+            draw::line((0, 0), (4, 2)),
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]
+        );
+        assert_eq!(draw::line((1, 3), (1, 1)), vec![(1, 3), (1, 2), (1, 1)]);
+        assert_eq!(draw::rectangle((2, 2), (0, 0), false).len(), 8);
+        assert_eq!(draw::rectangle((2, 2), (0, 0), true).len(), 9);
+
+        // a fast pencil stroke leaves no gaps and is undone in one go
+        let mut game = Game::new(false);
+        game.tool = Tool::Pencil;
+        game.press(game.cell_center((0, 0)));
+        game.drag(game.cell_center((3, 0)));
+        game.drag(game.cell_center((3, 2)));
+        game.release();
+        assert_eq!(game.universe.population(), 6);
+        // starting on a live cell erases
+        game.press(game.cell_center((3, 1)));
+        game.drag(game.cell_center((3, 0)));
+        game.release();
+        assert_eq!(game.universe.cells(), vec![(0, 0), (1, 0), (2, 0), (3, 2)]);
+        game.undo();
+        assert_eq!(game.universe.population(), 6);
+        game.undo();
+        assert_eq!(game.universe.population(), 0);
+
+        // shapes are only drawn when the drag ends
+        game.tool = Tool::Rectangle;
+        game.press(game.cell_center((-2, -2)));
+        game.drag(game.cell_center((2, 2)));
+        assert_eq!(game.preview().len(), 16);
+        assert_eq!(game.universe.population(), 0);
+        game.release();
+        assert_eq!(game.universe.population(), 16);
+        assert!(game.preview().is_empty());
+
+        // fills only go inside enclosed regions
+        game.tool = Tool::Fill;
+        assert!(!game.press(game.cell_center((5, 5))));
+        assert!(game.press(game.cell_center((0, 0))));
+        assert_eq!(game.universe.population(), 25);
+        game.undo();
+        game.tool = Tool::Line;
+        game.press(game.cell_center((-1, 0)));
+        game.drag(game.cell_center((1, 0)));
+        game.release();
+        game.tool = Tool::Fill;
+        assert!(game.press(game.cell_center((0, -1))));
+        assert_eq!(game.universe.population(), 16 + 3 + 3);
+
+        // a stroke keeps the tool it started with
+        let mut game = Game::new(false);
+        game.tool = Tool::Line;
+        game.press(game.cell_center((0, 0)));
+        game.drag(game.cell_center((4, 2)));
+        game.tool = Tool::FilledRectangle;
+        game.release();
+        assert_eq!(game.universe.population(), 5);
+
+        // in a bounded world fills go round joined edges and stop at walls
+        let n = SIZE as i32;
+        for (topology, filled) in [
+            (
+                Topology::Plane {
+                    width: 1,
+                    height: 1,
+                },
+                n - 11,
+            ),
+            (
+                Topology::Torus {
+                    width: 1,
+                    height: 1,
+                },
+                n - 1,
+            ),
+            (
+                Topology::VerticalCylinder {
+                    width: 1,
+                    height: 1,
+                },
+                n - 11,
+            ),
+        ] {
+            let mut game = Game::new(false);
+            game.set_topology(topology);
+            let line: Vec<_> = (0..n).map(|y| (10, y, 1)).collect();
+            game.set_states(&line);
+            game.tool = Tool::Fill;
+            assert!(game.press(game.cell_center((20, 5))));
+            assert_eq!(game.universe.population() as i32, (filled + 1) * n);
+        }
+    }
+    #[test]
+    fn soups() {
+        use crate::soup::{self, Random, Symmetry};
+        // SplitMix64's published outputs, so soups are the same everywhere
+        let mut random = Random::new(0);
+        assert_eq!(random.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(random.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+
+        let a = soup::soup(32, 32, 0.5, Symmetry::C1, 42);
+        assert_eq!(a, soup::soup(32, 32, 0.5, Symmetry::C1, 42));
+        assert_ne!(a, soup::soup(32, 32, 0.5, Symmetry::C1, 43));
+        assert!((400..624).contains(&a.cells.len()));
+        assert!(soup::soup(8, 8, 0.0, Symmetry::C1, 1).cells.is_empty());
+        assert_eq!(soup::soup(8, 8, 1.0, Symmetry::C1, 1).cells.len(), 64);
+
+        for &symmetry in Symmetry::ALL.iter() {
+            assert_eq!(symmetry.to_string().parse(), Ok(symmetry));
+            let (width, height) = symmetry.fit(20, 17);
+            let pattern = soup::soup(20, 17, 0.4, symmetry, 7);
+            let (_, _, right, bottom) = pattern.bounding_box().unwrap();
+            assert!(right < width && bottom < height, "{}", symmetry);
+            for &transform in symmetry.transforms() {
+                let mut image = pattern.clone();
+                image.transform_within(transform, (width, height));
+                assert_eq!(image, pattern, "{}", symmetry);
+            }
+        }
+        assert_eq!(Symmetry::D8_4.fit(20, 17), (16, 16));
+        assert_eq!(Symmetry::C2_2.fit(20, 17), (19, 16));
+        assert_eq!(Symmetry::D2Plus1.to_string(), "D2_+1");
+        assert!("D3".parse::<Symmetry>().is_err());
+
+        let mut game = Game::new(false);
+        game.universe.set_cell(3, 3, true);
+        game.universe.set_cell(40, 3, true);
+        let region = selection::Selection::new((0, 0), (15, 15));
+        let filled = game.soup(region, 0.5, Symmetry::C4_1, 9);
+        assert_eq!((filled.width(), filled.height()), (15, 15));
+        game.select((0, 0), (15, 15));
+        let mut pattern = game.selected_pattern().unwrap();
+        assert_eq!(
+            pattern.cells,
+            soup::soup(16, 16, 0.5, Symmetry::C4_1, 9).cells
+        );
+        pattern.transform_within(pattern::Transform::Rotate90, (15, 15));
+        assert_eq!(pattern, game.selected_pattern().unwrap());
+        assert!(game.universe.get_cell(40, 3));
+        game.undo();
+        assert_eq!(game.universe.cells(), vec![(3, 3), (40, 3)]);
+    }
+    #[test]
+    fn pattern_library() {
+        use crate::pattern::Transform;
+        let run = |name: &str, generations: u64| {
+            let mut universe = Universe::new();
+            universe.place(&library::get(name).unwrap(), (0, 0));
+            universe.step(generations);
+            let mut pattern = universe.pattern();
+            pattern.normalize();
+            pattern.cells
+        };
+        for name in library::names() {
+            assert!(library::get(name).is_some(), "{}", name);
+        }
+        // spaceships and oscillators come back after their period
+        for &(name, period) in [
+            ("Glider", 4),
+            ("LWSS", 4),
+            ("MWSS", 4),
+            ("HWSS", 4),
+            ("Pulsar", 3),
+            ("Pentadecathlon", 15),
+            ("Toad", 2),
+            ("Eater", 1),
+            ("Loaf", 1),
+        ]
+        .iter()
+        {
+            assert_eq!(run(name, period), run(name, 0), "{}", name);
+        }
+        // each gun adds a glider per period
+        assert_eq!(run("Gosper glider gun", 30).len(), 36 + 5);
+        assert_eq!(run("Simkin glider gun", 120).len(), 36 + 5);
+        assert_eq!(run("Diehard", 130), vec![]);
+        assert_eq!(
+            library::get("r pentomino").unwrap().name.as_deref(),
+            Some("R-pentomino")
+        );
+        assert!(library::get("gun").is_none());
+
+        let mut game = Game::new(false);
+        assert!(game.place_pattern("glider", 10, 20, Transform::Rotate90));
+        assert_eq!(
+            game.universe.cells(),
+            vec![(10, 20), (10, 21), (12, 21), (10, 22), (11, 22)]
+        );
+        assert!(!game.place_pattern("nothing", 0, 0, Transform::Identity));
+        game.undo();
+        assert_eq!(game.universe.population(), 0);
+        assert_eq!(game.browse(1), "Glider");
+        assert_eq!(game.browse(-1), library::PATTERNS.last().unwrap().0);
+        game.click(game.cell_center((0, 0)));
+        assert_eq!(game.universe.population(), 8);
+    }
+    #[test]
+    fn rle_read() {
+        let pattern = rle::read(
+            "#N Glider\n#C A small spaceship.\n#O Richard K. Guy\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n",
+        )
+        .unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(
+            pattern.comments,
+            vec!["A small spaceship.", "Richard K. Guy"]
+        );
+        assert_eq!(pattern.rule, Some(Rule::default()));
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let multiline = rle::read("x = 7, y = 5, rule = 23/36\n2o3b\n2o$$\n2$7o!").unwrap();
+        assert_eq!(multiline.rule, Some("B36/S23".parse().unwrap()));
+        assert_eq!(multiline.cells.len(), 11);
+        assert_eq!(multiline.cells[4], (0, 4));
+    }
+    #[test]
+    fn rle_errors() {
+        assert_eq!(rle::read("#N Empty\n"), Err(rle::RleError::MissingHeader));
+        assert_eq!(rle::read("y = 3\n3o!"), Err(rle::RleError::MissingHeader));
+        assert!(matches!(
+            rle::read("bo$2bo$3o!"),
+            Err(rle::RleError::InvalidHeader(_))
+        ));
+        assert_eq!(
+            rle::read("x = 3, y = 3\nbob$2bo$3o"),
+            Err(rle::RleError::MissingEnd)
+        );
+        assert_eq!(
+            rle::read("x = 3, y = 3\nbo?$3o!"),
+            Err(rle::RleError::UnexpectedChar('?', 2))
+        );
+        assert_eq!(
+            rle::read("x = 3, y = 3\nbox$3o!"),
+            Err(rle::RleError::UnexpectedChar('x', 2))
+        );
+        // other letters only stand for states under a multi-state rule
+        assert_eq!(
+            rle::read("x = 3, y = 1\nAo!"),
+            Err(rle::RleError::UnexpectedChar('A', 2))
+        );
+        assert_eq!(
+            rle::read("x = 3, y = 1, rule = /2/3\nA2Y!"),
+            Err(rle::RleError::UnexpectedChar('Y', 2))
+        );
+        assert_eq!(
+            rle::read("x = 3, y = 1, rule = /2/3\nAzB!"),
+            Err(rle::RleError::UnexpectedChar('z', 2))
+        );
+        // runs may not reach past the header's size, however long they are
+        assert_eq!(
+            rle::read("x = 3, y = 3\n2000000000o!"),
+            Err(rle::RleError::OutOfBounds(2))
+        );
+        assert_eq!(
+            rle::read("x = 3, y = 3\n2147483647b2147483647b!"),
+            Err(rle::RleError::OutOfBounds(2))
+        );
+        assert_eq!(
+            rle::read("x = 3, y = 2\n3o$\n3o$o!"),
+            Err(rle::RleError::OutOfBounds(3))
+        );
+        assert_eq!(
+            rle::read("x = 2, y = 1\nb2o!"),
+            Err(rle::RleError::OutOfBounds(2))
+        );
+        assert!(matches!(
+            rle::read("x = 3, y = 3, rule = B9/S23\n3o!"),
+            Err(rle::RleError::InvalidRule(_))
+        ));
+        assert!(matches!(
+            rle::read("x = three, y = 3\n3o!"),
+            Err(rle::RleError::InvalidHeader(_))
+        ));
+    }
+    #[test]
+    fn rle_write() {
+        let mut pattern = Pattern::from_cells(vec![(11, 5), (12, 6), (10, 7), (11, 7), (12, 7)]);
+        pattern.name = Some("Glider".to_string());
+        pattern.comments.push("moves south east".to_string());
+        pattern.rule = Some("B36/S23".parse().unwrap());
+        assert_eq!(
+            rle::write(&pattern),
+            "#N Glider\n#C moves south east\nx = 3, y = 3, rule = B36/S23\nbo$2bo$3o!\n"
+        );
+        let line = Pattern::from_cells((0..100).step_by(2).map(|x| (x, 0)).collect());
+        let text = rle::write(&line);
+        assert!(text.lines().all(|l| l.len() <= 70));
+        assert_eq!(rle::read(&text).unwrap().cells, line.cells);
+        assert_eq!(
+            rle::write(&Pattern::new()),
+            "x = 0, y = 0, rule = B3/S23\n!\n"
+        );
+    }
+    #[test]
+    fn game_rle() {
+        let mut game = Game::new(false);
+        let n = SIZE as i32;
+        game.load_rle("#N Blinker\nx = 3, y = 1, rule = B36/S23\n3o!", (n - 2, -1))
+            .unwrap();
+        assert_eq!(
+            game.universe.cells(),
+            vec![(n - 2, -1), (n - 1, -1), (n, -1)]
+        );
+        assert_eq!(game.universe.rule.to_string(), "B36/S23");
+        assert!(game.universe.map.contains_key(&[1, 1]));
+        game.iterate();
+        assert_eq!(
+            game.to_rle(),
+            "#N Blinker\nx = 1, y = 3, rule = B36/S23\no$o$o!\n"
+        );
+    }
+    #[test]
+    fn plaintext() {
+        let pattern =
+            plaintext::read("!Name: Glider\n!\n!A small spaceship.\n.O\n..O\nOOO\n").unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, vec!["", "A small spaceship."]);
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(
+            plaintext::write(&pattern),
+            "!Name: Glider\n!\n!A small spaceship.\n.O\n..O\nOOO\n"
+        );
+        let gap = Pattern::from_cells(vec![(3, 1), (5, 4)]);
+        assert_eq!(plaintext::write(&gap), "O\n.\n.\n..O\n");
+        assert_eq!(
+            plaintext::read(".O.\n.o.\n"),
+            Err(plaintext::PlaintextError::UnexpectedChar('o', 2))
+        );
+    }
+    #[test]
+    fn life106() {
+        let pattern =
+            life106::read("#Life 1.06\n#D Name: Glider\n#R 23/36\n1 -1\n2 0\n 0 1 \n1 1\n2 1\n")
+                .unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.rule, Some("B36/S23".parse().unwrap()));
+        assert_eq!(pattern.cells, vec![(1, -1), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(
+            life106::write(&pattern),
+            "#Life 1.06\n#D Name: Glider\n#R B36/S23\n1 -1\n2 0\n0 1\n1 1\n2 1\n"
+        );
+        assert_eq!(
+            life106::read("1 2\n"),
+            Err(life106::Life106Error::MissingHeader)
+        );
+        assert_eq!(
+            life106::read("#Life 1.06\n1 2\n1 2 3\n"),
+            Err(life106::Life106Error::InvalidLine(3))
+        );
+        assert_eq!(
+            life106::read("#Life 1.06\nx 2\n"),
+            Err(life106::Life106Error::InvalidLine(2))
+        );
+    }
+    #[test]
+    fn format_round_trip() {
+        let mut game = Game::new(false);
+        for &(x, y) in &[
+            (0, 0),
+            (1, 0),
+            (9, 0),
+            (-7, 3),
+            (30, 12),
+            (-15, -20),
+            (4, 4),
+        ] {
+            game.universe.set_cell(x, y, true);
+        }
+        let original = game.universe.cells();
+        let (min_x, min_y, _, _) = game.pattern().bounding_box().unwrap();
+        let texts = [
+            rle::write(&game.pattern()),
+            plaintext::write(&game.pattern()),
+            life106::write(&game.pattern()),
+        ];
+        let patterns = [
+            rle::read(&texts[0]).unwrap(),
+            plaintext::read(&texts[1]).unwrap(),
+            life106::read(&texts[2]).unwrap(),
+        ];
+        for (i, pattern) in patterns.iter().enumerate() {
+            // Life 1.06 keeps absolute coordinates, the others are relative
+            // to the bounding box.
+            let offset = if i == 2 { (0, 0) } else { (min_x, min_y) };
+            let mut copy = Game::new(false);
+            copy.load_pattern(pattern.clone(), offset);
+            assert_eq!(copy.universe.cells(), original);
+        }
+        let mut rle = patterns[0].clone();
+        rle.normalize();
+        let mut cells = patterns[1].clone();
+        cells.normalize();
+        let mut life = patterns[2].clone();
+        life.normalize();
+        assert_eq!(rle.cells, cells.cells);
+        assert_eq!(cells.cells, life.cells);
+    }
+    #[test]
+    fn save_round_trip() {
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!", (6, 6))
+            .unwrap();
+        game.iterate();
+        game.iterate();
+        game.pos((3.0, -2.0));
+        game.zoom(true);
+        let bytes = save::write(&game.save_data());
+        assert_eq!(&bytes[0..4], &save::MAGIC);
+        assert_eq!(&bytes[4..6], &save::VERSION.to_le_bytes());
+        let mut copy = Game::new(false);
+        copy.load_save_data(save::read(&bytes).unwrap());
+        assert_eq!(copy.universe.cells(), game.universe.cells());
+        assert_eq!(copy.universe.rule, game.universe.rule);
+        assert_eq!(copy.universe.generation, 2);
+        assert_eq!(copy.bit_size, game.bit_size);
+        assert_eq!(copy.relative_pos, game.relative_pos);
+        copy.iterate();
+        game.iterate();
+        assert_eq!(copy.universe.cells(), game.universe.cells());
+    }
+    #[test]
+    fn save_errors() {
+        let mut game = Game::new(false);
+        game.universe.set_cell(3, 3, true);
+        let bytes = save::write(&game.save_data());
+        let mut corrupt = bytes.clone();
+        corrupt[20] ^= 0xff;
+        assert!(matches!(
+            save::read(&corrupt),
+            Err(save::SaveError::ChecksumMismatch { .. })
+        ));
+        // as must one cut short, rather than reporting where it ran out
+        assert!(matches!(
+            save::read(&bytes[..bytes.len() - 10]),
+            Err(save::SaveError::ChecksumMismatch { .. })
+        ));
+        let mut newer = bytes[..bytes.len() - 4].to_vec();
+        newer[4] = 0xff;
+        let checksum = save::crc32(&newer);
+        newer.extend_from_slice(&checksum.to_le_bytes());
+        assert!(matches!(
+            save::read(&newer),
+            Err(save::SaveError::UnsupportedVersion(0xff))
+        ));
+        assert!(matches!(
+            save::read(&bytes[..5]),
+            Err(save::SaveError::Truncated(_))
+        ));
+        // a truncated file that still checksums must report where it ended
+        let mut short = bytes[..bytes.len() - 10].to_vec();
+        let checksum = save::crc32(&short);
+        short.extend_from_slice(&checksum.to_le_bytes());
+        assert!(matches!(
+            save::read(&short),
+            Err(save::SaveError::Truncated("chunk records"))
+        ));
+        // the chunk's plane count is the byte before the checksum
+        let mut planes = bytes[..bytes.len() - 4].to_vec();
+        *planes.last_mut().unwrap() = 9;
+        let checksum = save::crc32(&planes);
+        planes.extend_from_slice(&checksum.to_le_bytes());
+        assert!(matches!(
+            save::read(&planes),
+            Err(save::SaveError::TooManyPlanes(9))
+        ));
+        assert_eq!(save::crc32(b"123456789"), 0xcbf4_3926);
+    }
+    #[test]
+    fn save_legacy() {
+        let mut bytes: Vec<u8> = Vec::new();
+        for &(pos, row) in &[([0, 0], 0b0111_0000u8), ([-1, 2], 0b1000_0001)] {
+            let pos: [i32; 2] = pos;
+            bytes.extend_from_slice(&pos[0].to_ne_bytes());
+            bytes.extend_from_slice(&pos[1].to_ne_bytes());
+            bytes.extend_from_slice(&[row, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        let data = save::read(&bytes).unwrap();
+        assert_eq!(data.rule, Rule::default());
+        assert_eq!(data.chunks.len(), 2);
+        let mut game = Game::new(false);
+        game.load_save_data(data);
+        assert_eq!(
+            game.universe.cells(),
+            vec![(-8, -16), (-1, -16), (1, 0), (2, 0), (3, 0)]
+        );
+        bytes.pop();
+        assert!(matches!(
+            save::read(&bytes),
+            Err(save::SaveError::Truncated(_))
+        ));
+        // version 1 files hold 8x8 chunks without a size field
+        let mut v1: Vec<u8> = Vec::new();
+        v1.extend_from_slice(&save::MAGIC);
+        v1.extend_from_slice(&1u16.to_le_bytes());
+        v1.extend_from_slice(&6u16.to_le_bytes());
+        v1.extend_from_slice(b"B3/S23");
+        v1.extend_from_slice(&7u64.to_le_bytes());
+        for f in &[10.0f32, 0.0, 0.0] {
+            v1.extend_from_slice(&f.to_le_bytes());
+        }
+        v1.extend_from_slice(&1u32.to_le_bytes());
+        v1.extend_from_slice(&(-1i32).to_le_bytes());
+        v1.extend_from_slice(&1i32.to_le_bytes());
+        v1.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0b0000_0011]);
+        let checksum = save::crc32(&v1);
+        v1.extend_from_slice(&checksum.to_le_bytes());
+        let data = save::read(&v1).unwrap();
+        assert_eq!(data.generation, 7);
+        let mut game = Game::new(false);
+        game.load_save_data(data);
+        assert_eq!(game.universe.cells(), vec![(-2, -1), (-1, -1)]);
+        let mut odd = save::write(&game.save_data());
+        odd.truncate(odd.len() - 4);
+        odd[save::MAGIC.len() + 2 + 2 + 6 + 4 + 8 + 12] = 12;
+        let checksum = save::crc32(&odd);
+        odd.extend_from_slice(&checksum.to_le_bytes());
+        assert!(matches!(
+            save::read(&odd),
+            Err(save::SaveError::UnsupportedChunkSize(12))
+        ));
+    }
+    #[test]
+    fn file_formats() {
+        use files::FileFormat;
+        use std::path::Path;
+        assert_eq!(
+            FileFormat::from_path(Path::new("a/b.cgl")),
+            Some(FileFormat::Cgl)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("gun.RLE")),
+            Some(FileFormat::Rle)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("glider.cells")),
+            Some(FileFormat::Plaintext)
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("acorn.lif")),
+            Some(FileFormat::Life106)
+        );
+        assert_eq!(FileFormat::from_path(Path::new("save")), None);
+        assert_eq!(FileFormat::from_path(Path::new("notes.txt")), None);
+    }
+    #[test]
+    fn game_save_open() {
+        let dir = std::env::temp_dir().join(format!("life-rust-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut game = Game::new(false);
+        game.load_rle("x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!", (20, 30))
+            .unwrap();
+        for name in &["save.cgl", "save.rle", "save.cells", "save.lif"] {
+            let path = dir.join(name);
+            game.save(&path).unwrap();
+            assert_eq!(game.path.as_deref(), Some(path.as_path()));
+            let mut copy = Game::new(false);
+            copy.open(&path).unwrap();
+            let mut cells = Pattern::from_cells(copy.universe.cells());
+            if *name != "save.cgl" {
+                assert_eq!(cells.bounding_box(), Some((-1, -1, 1, 1)));
+            }
+            cells.normalize();
+            assert_eq!(cells.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        }
+        // Life 1.06 coordinates can be anywhere, negative ones included
+        std::fs::write(dir.join("far.lif"), "#Life 1.06\n-40 -9\n-36 -5\n").unwrap();
+        let mut copy = Game::new(false);
+        copy.open(&dir.join("far.lif")).unwrap();
+        assert_eq!(copy.universe.cells(), vec![(-2, -2), (2, 2)]);
+        let mut copy = Game::new(false);
+        copy.open(&dir.join("save.cgl")).unwrap();
+        assert_eq!(copy.universe.cells(), game.universe.cells());
+        assert_eq!(copy.universe.rule, game.universe.rule);
+        assert!(matches!(
+            game.save(&dir.join("save.txt")),
+            Err(files::FileError::UnknownFormat(_))
+        ));
+        assert!(matches!(
+            copy.open(&dir.join("missing.rle")),
+            Err(files::FileError::Io(_))
+        ));
+        std::fs::write(dir.join("bad.rle"), "x = 3, y = 1\n3o").unwrap();
+        assert!(matches!(
+            copy.open(&dir.join("bad.rle")),
+            Err(files::FileError::Rle(rle::RleError::MissingEnd))
+        ));
+        // opening replaces whatever HashLife had run ahead to
+        let mut ahead = Game::new(false);
+        ahead.toggle_engine();
+        ahead.load_rle("x = 2, y = 2\n2o$2o!", (100, 100)).unwrap();
+        ahead.jump();
+        ahead.open(&dir.join("save.rle")).unwrap();
+        ahead.sync();
+        assert_eq!(ahead.universe.population(), 5);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn recent_files() {
+        use files::RecentFiles;
+        use std::path::PathBuf;
+        let dir = std::env::temp_dir().join(format!("life-rust-recent-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let list = dir.join("recent");
+        let mut recent = RecentFiles::load(list.clone());
+        assert!(recent.paths.is_empty());
+        for i in 0..12 {
+            recent.push(&PathBuf::from(format!("/patterns/{}.rle", i)));
+        }
+        recent.push(&PathBuf::from("/patterns/5.rle"));
+        assert_eq!(recent.paths.len(), RecentFiles::LIMIT);
+        assert_eq!(
+            recent.get(0),
+            Some(PathBuf::from("/patterns/5.rle").as_path())
+        );
+        assert_eq!(
+            recent.get(1),
+            Some(PathBuf::from("/patterns/11.rle").as_path())
+        );
+        let reloaded = RecentFiles::load(list);
+        assert_eq!(reloaded.paths, recent.paths);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn hashlife_glider() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut life = HashLife::from_cells(Rule::CONWAY, &glider);
+        assert_eq!(life.bounding_box(), Some((0, 0, 2, 2)));
+        life.step_pow2(10);
+        assert_eq!(life.generation(), 1024);
+        assert_eq!(life.population(), 5);
+        let moved: Vec<(i32, i32)> = glider.iter().map(|&(x, y)| (x + 256, y + 256)).collect();
+        let mut expected = moved.clone();
+        expected.sort_unstable_by_key(|&(x, y)| (y, x));
+        assert_eq!(life.cells(), expected);
+        assert!(life.get_cell(257, 256));
+        assert!(!life.get_cell(1, 0));
+    }
+    #[test]
+    fn hashlife_matches_chunks() {
+        fn run<E: Engine>(engine: &mut E, steps: &[u64]) -> Vec<Vec<(i32, i32)>> {
+            let mut history = Vec::new();
+            for &n in steps {
+                engine.step(n);
+                history.push(engine.cells());
+            }
+            history
+        }
+        let r_pentomino = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)];
+        let steps = [1, 2, 7, 30, 60];
+        for rule in ["B3/S23", "B36/S23", "B3678/S34678", "B3/S2-i34q", "B2/S34H"].iter() {
+            let rule: Rule = rule.parse().unwrap();
+            let mut universe = Universe::new();
+            universe.rule = rule;
+            let mut life = HashLife::new(rule);
+            for &(x, y) in r_pentomino.iter() {
+                Engine::set_cell(&mut universe, x, y, true);
+                life.set_cell(x, y, true);
+            }
+            assert_eq!(run(&mut universe, &steps), run(&mut life, &steps));
+            assert_eq!(Engine::population(&universe), life.population());
+            assert_eq!(Engine::bounding_box(&universe), life.bounding_box());
+            assert_eq!(Engine::generation(&universe), 100);
+            assert_eq!(life.generation(), 100);
+        }
+    }
+    #[test]
+    fn game_engines() {
+        let mut chunks = Game::new(false);
+        let mut hashlife = Game::new(false);
+        hashlife.toggle_engine();
+        assert_eq!(hashlife.engine, EngineKind::HashLife);
+        for game in [&mut chunks, &mut hashlife].iter_mut() {
+            game.load_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0)).unwrap();
+            game.step(50);
+            game.iterate();
+        }
+        // the tree holds the pattern until something needs the cells
+        assert_eq!(hashlife.generation(), 51);
+        assert_eq!(hashlife.universe.generation, 0);
+        hashlife.sync();
+        assert_eq!(hashlife.universe.generation, 51);
+        assert_eq!(hashlife.universe.cells(), chunks.universe.cells());
+        // edits made between steps are picked up by the tree
+        for game in [&mut chunks, &mut hashlife].iter_mut() {
+            game.sync();
+            for &(x, y) in &[(-20, -20), (-20, -19), (-19, -20), (-19, -19)] {
+                game.universe.set_cell(x, y, true);
+            }
+            game.step(20);
+        }
+        assert_eq!(hashlife.pattern().cells, chunks.pattern().cells);
+        hashlife.jump_exponent = 3;
+        assert_eq!(hashlife.jump(), 8);
+        assert_eq!(hashlife.generation(), 79);
+        hashlife.sync();
+        assert_eq!(hashlife.universe.population(), 9);
+        // a jump stops short of carrying cells off the edge of the plane
+        let mut edge = Game::new(false);
+        edge.toggle_engine();
+        edge.load_rle("x = 3, y = 3\nbo$2bo$3o!", (i32::MAX - 1000, 0))
+            .unwrap();
+        edge.jump_exponent = 60;
+        let made = edge.jump();
+        assert!(made < 1000);
+        assert_eq!(edge.generation(), made);
+        edge.sync();
+        assert_eq!(edge.universe.population(), 5);
+    }
+    #[test]
+    fn bit_parallel_step() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let rules: Vec<Rule> = [
+            "B3/S23",
+            "B36/S23",
+            "B1357/S1357",
+            "B2/S",
+            "B2-a/S12",
+            "B3/S2-i34q",
+            "B2ce3aik/S1e2-kn3q8",
+            "B2/S34H",
+            "B13/S012V",
+        ]
+        .iter()
+        .map(|r| r.parse().unwrap())
+        .collect();
+        for _ in 0..100 {
+            let mut rows = [0; SIZE];
+            for row in rows.iter_mut() {
+                *row = next() as Row;
+            }
+            let chunk = Chunk::from([0, 0], rows);
+            let edges = Edges {
+                left: next() as Row,
+                right: next() as Row,
+                top: next() as Row,
+                bottom: next() as Row,
+                corners: next() as u8 & 0xf0,
+            };
+            for rule in rules.iter() {
+                let mut by_cell = chunk.clone();
+                let mut parallel = chunk.clone();
+                assert_eq!(
+                    by_cell.iterate_by_cell(&edges, rule),
+                    parallel.iterate(&edges, rule)
+                );
+                assert_eq!(by_cell, parallel);
+            }
+        }
+        let mut empty = Chunk::new([0, 0]);
+        assert_eq!(empty.iterate(&Edges::default(), &Rule::CONWAY), 0);
+        assert!(!empty.active);
+    }
+    #[test]
+    fn double_buffer() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut universe = Universe::new();
+        for &(x, y) in glider.iter() {
+            universe.set_cell(x, y, true);
+        }
+        let mut life = HashLife::from_cells(Rule::CONWAY, &glider);
+        for _ in 0..3 * SIZE {
+            universe.iterate();
+            // chunks the glider has left must not come back from the buffer
+            assert!(universe.map.len() <= 9);
+        }
+        life.step(3 * SIZE as u64);
+        assert_eq!(universe.cells(), life.cells());
+        let copy = universe.clone();
+        universe.iterate();
+        assert_eq!(copy.generation + 1, universe.generation);
+        assert_eq!(copy.population(), universe.population());
+    }
+    #[test]
+    fn threaded_step() {
+        let mut state: u64 = 0x853c_49e6_748f_ea9b;
+        let mut single = Universe::new();
+        single.threads = 1;
+        let n = 10 * SIZE as i32;
+        for y in 0..n {
+            for x in 0..n {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                if state % 3 == 0 {
+                    single.set_cell(x, y, true);
+                }
+            }
+        }
+        assert!(single.map.len() >= 3 * Universe::MIN_BATCH);
+        let mut threaded: Vec<Universe> = [2, 3, 8]
+            .iter()
+            .map(|&threads| {
+                let mut universe = single.clone();
+                universe.threads = threads;
+                universe
+            })
+            .collect();
+        for _ in 0..10 {
+            single.iterate();
+            for universe in threaded.iter_mut() {
+                universe.iterate();
+            }
+        }
+        let mut keys: Vec<[i32; 2]> = single.map.keys().copied().collect();
+        keys.sort_unstable();
+        for universe in threaded.iter() {
+            assert_eq!(universe.cells(), single.cells());
+            let mut other: Vec<[i32; 2]> = universe.map.keys().copied().collect();
+            other.sort_unstable();
+            assert_eq!(other, keys);
+        }
+    }
+    #[test]
+    fn torus() {
+        let n = SIZE as i32;
+        let mut universe = Universe::new();
+        universe.set_topology(Topology::Torus {
+            width: 2,
+            height: 1,
+        });
+        // cells wrap into the world
+        universe.set_cell(-1, 3, true);
+        universe.set_cell(2 * n + 1, n + 3, true);
+        assert_eq!(universe.cells(), vec![(1, 3), (2 * n - 1, 3)]);
+        assert!(universe.get_cell(-1, 3 - n));
+        assert_eq!(universe.edges([0, 0], 0).left, 1 << (SIZE - 4));
+        assert_eq!(universe.edges([1, 0], 0).right, 0);
+        assert_eq!(universe.edges([0, 0], 0).top, 0);
+        universe.set_cell(0, n - 1, true);
+        assert_eq!(universe.edges([0, 0], 0).top, 1 << (SIZE - 1));
+        // a corner cell is a corner neighbour of every chunk touching it
+        assert_eq!(universe.corners([1, 0]) & 0b0001_0000, 0b0001_0000);
+        // a blinker across the seam keeps oscillating and no chunks leak out
+        universe.set_topology(Topology::Torus {
+            width: 1,
+            height: 1,
+        });
+        universe.clear();
+        for x in -1..=1 {
+            universe.set_cell(x, 0, true);
+        }
+        universe.iterate();
+        assert_eq!(universe.cells(), vec![(0, 0), (0, 1), (0, n - 1)]);
+        universe.iterate();
+        assert_eq!(universe.cells(), vec![(0, 0), (1, 0), (n - 1, 0)]);
+        assert_eq!(universe.map.keys().collect::<Vec<_>>(), vec![&[0, 0]]);
+    }
+    #[test]
+    fn torus_glider() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut game = Game::new(false);
+        game.set_topology(Topology::Torus {
+            width: 1,
+            height: 1,
+        });
+        // HashLife can't run on a torus, so it can't be picked
+        assert!(!game.toggle_engine());
+        assert_eq!(game.engine_in_use(), EngineKind::Chunks);
+        for &(x, y) in glider.iter() {
+            game.universe.set_cell(x, y, true);
+        }
+        let start = game.universe.cells();
+        // a glider moves one cell diagonally every four generations
+        game.step(2 * SIZE as u64);
+        assert_ne!(game.universe.cells(), start);
+        assert_eq!(game.universe.population(), 5);
+        game.step(2 * SIZE as u64);
+        assert_eq!(game.universe.cells(), start);
+        assert_eq!(game.universe.map.len(), 1);
+        // and a world bounded after picking it falls back to chunks
+        let mut game = Game::new(false);
+        assert!(game.toggle_engine());
+        game.set_topology(Topology::Torus {
+            width: 1,
+            height: 1,
+        });
+        assert_eq!(game.engine, EngineKind::HashLife);
+        assert_eq!(game.engine_in_use(), EngineKind::Chunks);
+    }
+    #[test]
+    fn topologies() {
+        let n = SIZE as i32;
+        let parsed: Topology = "klein:4x3".parse().unwrap();
+        assert_eq!(
+            parsed,
+            Topology::KleinBottle {
+                width: 4,
+                height: 3
+            }
+        );
+        assert_eq!(parsed.to_string(), "klein:4x3");
+        assert_eq!("Unbounded".parse(), Ok(Topology::Unbounded));
+        assert!("torus".parse::<Topology>().is_err());
+        assert!("plane:0x3".parse::<Topology>().is_err());
+        assert!("sphere:2x2".parse::<Topology>().is_err());
+        // sizes whose cells wouldn't fit in i32 coordinates
+        assert_eq!(
+            "torus:100000000x10".parse::<Topology>(),
+            Err(ParseTopologyError::TooLarge(100_000_000, 10))
+        );
+        assert_eq!(Topology::new(2, 10, i32::MAX / n + 1), None);
+        assert!(Topology::new(2, 10, i32::MAX / n).is_some());
+        let plane = Topology::Plane {
+            width: 1,
+            height: 1,
+        };
+        assert_eq!(plane.wrap_cell(-1, 0), None);
+        assert_eq!(plane.wrap_chunk([0, 1]), None);
+        let klein = Topology::KleinBottle {
+            width: 2,
+            height: 1,
+        };
+        assert_eq!(klein.wrap_cell(-1, 3), Some((2 * n - 1, 3)));
+        assert_eq!(klein.wrap_cell(3, -1), Some((2 * n - 4, n - 1)));
+        assert_eq!(klein.wrap_chunk([0, 1]), Some([1, 0]));
+        let cross = Topology::CrossSurface {
+            width: 1,
+            height: 1,
+        };
+        assert_eq!(cross.wrap_cell(-1, 5), Some((n - 1, n - 6)));
+
+        let blinker = |topology: Topology, cells: &[(i32, i32)]| {
+            let mut universe = Universe::new();
+            universe.set_topology(topology);
+            for &(x, y) in cells {
+                universe.set_cell(x, y, true);
+            }
+            let mut generations = Vec::new();
+            for _ in 0..2 {
+                universe.iterate();
+                generations.push(universe.cells());
+            }
+            generations
+        };
+        // a wall clips the blinker, which then dies out
+        let clipped = blinker(plane, &[(4, 0), (5, 0), (6, 0)]);
+        assert_eq!(clipped[0], vec![(5, 0), (5, 1)]);
+        assert!(clipped[1].is_empty());
+        let cylinder = Topology::HorizontalCylinder {
+            width: 1,
+            height: 1,
+        };
+        let across = blinker(cylinder, &[(-1, 5), (0, 5), (1, 5)]);
+        assert_eq!(across[0], vec![(0, 4), (0, 5), (0, 6)]);
+        assert_eq!(across[1], vec![(0, 5), (1, 5), (n - 1, 5)]);
+        let cylinder = Topology::VerticalCylinder {
+            width: 1,
+            height: 1,
+        };
+        assert_eq!(blinker(cylinder, &[(-1, 5), (0, 5), (1, 5)])[1].len(), 0);
+        // across a twisted edge the far end of the blinker is mirrored
+        let klein = Topology::KleinBottle {
+            width: 1,
+            height: 1,
+        };
+        let twisted = blinker(klein, &[(4, 0), (5, 0), (6, 0)]);
+        assert_eq!(twisted[0], vec![(5, 0), (5, 1), (n - 6, n - 1)]);
+        assert_eq!(twisted[1], vec![(4, 0), (5, 0), (6, 0)]);
+        let twisted = blinker(cross, &[(-1, 5), (0, 5), (1, 5)]);
+        assert_eq!(twisted[0], vec![(0, 4), (0, 5), (0, 6)]);
+        assert_eq!(twisted[1], vec![(0, 5), (1, 5), (n - 1, n - 6)]);
+    }
+    #[test]
+    fn topology_saved() {
+        let mut game = Game::new(false);
+        game.set_topology(Topology::CrossSurface {
+            width: 3,
+            height: 2,
+        });
+        game.universe.set_cell(-1, 5, true);
+        let bytes = save::write(&game.save_data());
+        let mut copy = Game::new(false);
+        copy.load_save_data(save::read(&bytes).unwrap());
+        assert_eq!(copy.universe.topology, game.universe.topology);
+        assert_eq!(
+            copy.universe.cells(),
+            vec![(3 * SIZE as i32 - 1, 2 * SIZE as i32 - 6)]
+        );
+        let mut bad = bytes[..bytes.len() - 4].to_vec();
+        bad[save::MAGIC.len() + 2 + 2 + 6 + 4 + 8 + 12 + 2] = 9;
+        let checksum = save::crc32(&bad);
+        bad.extend_from_slice(&checksum.to_le_bytes());
+        assert!(matches!(
+            save::read(&bad),
+            Err(save::SaveError::InvalidTopology(9, 3, 2))
+        ));
+    }
+    // An 8x8 pattern in the top left corner of an otherwise empty chunk.
+    fn rows(bytes: [u8; 8]) -> [Row; SIZE] {
+        let mut rows = [0; SIZE];
+        for (row, &byte) in rows.iter_mut().zip(bytes.iter()) {
+            *row = (byte as Row) << (SIZE - 8);
+        }
+        rows
+    }
+    // The top left 8 cells of a row.
+    fn byte(row: Row) -> u8 {
+        (row >> (SIZE - 8)) as u8
+    }
+    fn chunks_eq(c1: &Chunk, c2: &Chunk) -> bool {
+        let mut val = true;
+        for i in 0..SIZE {
+            if c1.get_byte_at(i) != c2.get_byte_at(i) {
+                val = false;
+            }
         }
+        val
     }
-    val
 }
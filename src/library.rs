@@ -0,0 +1,70 @@
+use crate::pattern::Pattern;
+use crate::rle;
+
+// Built-in Life patterns as RLE, by name.
+pub const PATTERNS: [(&str, &str); 19] = [
+    ("Glider", "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!"),
+    ("LWSS", "x = 5, y = 4, rule = B3/S23\nbo2bo$o4b$o3bo$4o!"),
+    (
+        "MWSS",
+        "x = 6, y = 5, rule = B3/S23\n3bo2b$bo3bo$o5b$o4bo$5o!",
+    ),
+    (
+        "HWSS",
+        "x = 7, y = 5, rule = B3/S23\n3b2o2b$bo4bo$o6b$o5bo$6o!",
+    ),
+    (
+        "Gosper glider gun",
+        "x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$
+10bo5bo7bo$11bo3bo$12b2o!",
+    ),
+    (
+        "Simkin glider gun",
+        "x = 33, y = 21, rule = B3/S23
+2o5b2o$2o5b2o2$4b2o$4b2o5$22b2ob2o$21bo5bo$21bo6bo2b2o$21b3o3bo3b2o$26bo
+4$20b2o$20bo$21b3o$23bo!",
+    ),
+    (
+        "Pulsar",
+        "x = 13, y = 13, rule = B3/S23
+2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$
+o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    ),
+    (
+        "Pentadecathlon",
+        "x = 10, y = 3, rule = B3/S23\n2bo4bo2b$2ob4ob2o$2bo4bo!",
+    ),
+    ("R-pentomino", "x = 3, y = 3, rule = B3/S23\nb2o$2o$bo!"),
+    ("Acorn", "x = 7, y = 3, rule = B3/S23\nbo5b$3bo3b$2o2b3o!"),
+    ("Diehard", "x = 8, y = 3, rule = B3/S23\n6bob$2o6b$bo3b3o!"),
+    // Eater 1, the fishhook.
+    ("Eater", "x = 4, y = 4, rule = B3/S23\n2o2b$obob$2bob$2b2o!"),
+    ("Block", "x = 2, y = 2, rule = B3/S23\n2o$2o!"),
+    ("Beehive", "x = 4, y = 3, rule = B3/S23\nb2o$o2bo$b2o!"),
+    ("Loaf", "x = 4, y = 4, rule = B3/S23\nb2o$o2bo$bobo$2bo!"),
+    ("Boat", "x = 3, y = 3, rule = B3/S23\n2o$obo$bo!"),
+    ("Blinker", "x = 3, y = 1, rule = B3/S23\n3o!"),
+    ("Toad", "x = 4, y = 2, rule = B3/S23\nb3o$3o!"),
+    ("Beacon", "x = 4, y = 4, rule = B3/S23\n2o$2o$2b2o$2b2o!"),
+];
+
+// The pattern with this name, ignoring case, spaces and punctuation (so
+// "r pentomino" finds "R-pentomino"), with its top left at (0, 0).
+pub fn get(name: &str) -> Option<Pattern> {
+    let key = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+    let (name, text) = PATTERNS.iter().find(|(n, _)| key(n) == key(name))?;
+    let mut pattern = rle::read(text).ok()?;
+    pattern.name = Some(name.to_string());
+    pattern.normalize();
+    Some(pattern)
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PATTERNS.iter().map(|(name, _)| *name)
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use crate::pattern::Pattern;
+use crate::rule::ParseRuleError;
+
+// Reader and writer for Life 1.06 coordinate lists:
+//
+//     #Life 1.06
+//     1 0
+//     2 1
+//     0 2
+
+const HEADER: &str = "#Life 1.06";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Life106Error {
+    MissingHeader,
+    InvalidLine(usize),
+    InvalidRule(ParseRuleError),
+}
+impl fmt::Display for Life106Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Life106Error::MissingHeader => write!(f, "file does not start with {:?}", HEADER),
+            Life106Error::InvalidLine(line) => {
+                write!(f, "line {} is not an \"x y\" coordinate pair", line)
+            }
+            Life106Error::InvalidRule(e) => write!(f, "invalid Life 1.06 rule: {}", e),
+        }
+    }
+}
+impl std::error::Error for Life106Error {}
+impl From<ParseRuleError> for Life106Error {
+    fn from(e: ParseRuleError) -> Life106Error {
+        Life106Error::InvalidRule(e)
+    }
+}
+
+pub fn read(text: &str) -> Result<Pattern, Life106Error> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.trim() == HEADER => {}
+        _ => return Err(Life106Error::MissingHeader),
+    }
+    let mut pattern = Pattern::new();
+    for (n, line) in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix("#D") {
+            let comment = comment.trim();
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.to_string()),
+            }
+            continue;
+        }
+        if let Some(rule) = line.strip_prefix("#R") {
+            pattern.rule = Some(rule.parse()?);
+            continue;
+        }
+        let mut coords = line.split_whitespace().map(|c| c.parse::<i32>());
+        match (coords.next(), coords.next(), coords.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => pattern.cells.push((x, y)),
+            _ => return Err(Life106Error::InvalidLine(n + 1)),
+        }
+    }
+    pattern.sort();
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{}\n", HEADER);
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#D Name: {}\n", name));
+    }
+    for comment in pattern.comments.iter() {
+        out.push_str(&format!("#D {}\n", comment));
+    }
+    if let Some(rule) = pattern.rule {
+        out.push_str(&format!("#R {}\n", rule));
+    }
+    let mut cells = pattern.cells.clone();
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    for (x, y) in cells {
+        out.push_str(&format!("{} {}\n", x, y));
+    }
+    out
+}
@@ -1,156 +1,175 @@
-extern crate kiss3d;
-
 use kiss3d::event::{Action, WindowEvent};
 use kiss3d::light::Light;
 use kiss3d::nalgebra::{Point2, Vector2};
 use kiss3d::planar_camera::*;
 use kiss3d::window::Window;
 
-mod chunk;
-mod game;
+use life_rust::draw::Tool;
+use life_rust::files::{FileFormat, RecentFiles};
+use life_rust::game::Game;
+use life_rust::pattern::Transform;
+use life_rust::rule::Rule;
+use life_rust::selection::Selection;
+use life_rust::soup::Symmetry;
+use life_rust::table::RuleTable;
+use life_rust::topology::Topology;
+use life_rust::view::View;
+
+use std::path::{Path, PathBuf};
 
-use chunk::Chunk;
-use game::Game;
+const RECENT_KEYS: [kiss3d::event::Key; RecentFiles::LIMIT] = [
+    kiss3d::event::Key::Key1,
+    kiss3d::event::Key::Key2,
+    kiss3d::event::Key::Key3,
+    kiss3d::event::Key::Key4,
+    kiss3d::event::Key::Key5,
+    kiss3d::event::Key::Key6,
+    kiss3d::event::Key::Key7,
+    kiss3d::event::Key::Key8,
+    kiss3d::event::Key::Key9,
+];
 
-use std::thread::sleep;
-use std::time::Duration;
+const SCRUB_KEYS: [kiss3d::event::Key; 10] = [
+    kiss3d::event::Key::Key1,
+    kiss3d::event::Key::Key2,
+    kiss3d::event::Key::Key3,
+    kiss3d::event::Key::Key4,
+    kiss3d::event::Key::Key5,
+    kiss3d::event::Key::Key6,
+    kiss3d::event::Key::Key7,
+    kiss3d::event::Key::Key8,
+    kiss3d::event::Key::Key9,
+    kiss3d::event::Key::Key0,
+];
 
 fn main() {
     let mut window = Window::new("Conway's Game of Life");
     let mut camera = kiss3d::planar_camera::FixedView::new();
     window.set_light(Light::StickToCamera);
-    let mut game = Game::from(
-        [
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-            0b0000_0000,
-        ],
-        true,
-    );
-    for x in -3..4 {
-        for y in -3..4 {
-            if !(x == 0 && y == 0) {
-                game.insert_chunk([x, y], Chunk::new([x, y], 10.0, (0.0, 0.0)));
+    let mut game = Game::new(true);
+    game.place_pattern("Gosper glider gun", -23, -24, Transform::Identity);
+    // Soups from N: the seed goes up by one for each.
+    let mut seed: u64 = 1;
+    let mut density: f64 = 0.5;
+    let mut symmetry = Symmetry::C1;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--threads" || arg == "-j" {
+            match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => game.universe.threads = n,
+                _ => {
+                    println!("{} needs a thread count", arg);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg == "--history" {
+            match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => game.history.set_limit(n),
+                _ => {
+                    println!("{} needs a number of edits", arg);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg == "--rewind" {
+            match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => game.rewind.set_capacity(n),
+                _ => {
+                    println!("{} needs a number of generations", arg);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg == "--rewind-every" {
+            match args.next().map(|n| n.parse::<u64>()) {
+                Some(Ok(n)) if n > 0 => game.rewind.set_stride(n),
+                _ => {
+                    println!("{} needs a positive number of generations", arg);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg == "--seed" {
+            match args.next().map(|n| n.parse::<u64>()) {
+                Some(Ok(n)) => seed = n,
+                _ => {
+                    println!("{} needs a number", arg);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg == "--density" {
+            match args.next().map(|n| n.parse::<f64>()) {
+                Some(Ok(d)) if (0.0..=1.0).contains(&d) => density = d,
+                _ => {
+                    println!("{} needs a number from 0 to 1", arg);
+                    return;
+                }
             }
+            continue;
         }
+        if arg == "--symmetry" {
+            match args.next().unwrap_or_default().parse::<Symmetry>() {
+                Ok(s) => symmetry = s,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg == "--topology" || arg == "--torus" {
+            let value = args.next().unwrap_or_default();
+            let value = if arg == "--torus" {
+                format!("torus:{}", value)
+            } else {
+                value
+            };
+            match value.parse::<Topology>() {
+                Ok(topology) => game.set_topology(topology),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+            continue;
+        }
+        if arg.ends_with(".rule") {
+            if let Err(e) = game.open(Path::new(&arg)) {
+                println!("{}: {}", arg, e);
+                return;
+            }
+            continue;
+        }
+        match arg.parse::<Rule>() {
+            Ok(rule) => game.set_rule(rule),
+            Err(e) => match RuleTable::builtin(&arg) {
+                Some(table) => game.set_table(table),
+                None => {
+                    println!("{}", e);
+                    return;
+                }
+            },
+        }
+    }
+    // The starting pattern isn't something to undo.
+    game.history.clear();
+    let mut recent = RecentFiles::load_default();
+    for (i, path) in recent.paths.iter().enumerate() {
+        println!("Ctrl+{}: {}", i + 1, path.display());
     }
-    game.insert_chunk(
-        [-3, 3],
-        Chunk::from(
-            [-3, 3],
-            [
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0110_0000,
-                0b0110_0000,
-                0b0000_0000,
-                0b0000_0000,
-            ],
-            10.0,
-            (0.0, 0.0),
-        ),
-    );
-    game.insert_chunk(
-        [-2, 3],
-        Chunk::from(
-            [-2, 3],
-            [
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0110,
-                0b0000_1000,
-                0b0001_0000,
-                0b0001_0001,
-                0b0001_0000,
-                0b0000_1000,
-            ],
-            10.0,
-            (0.0, 0.0),
-        ),
-    );
-    game.insert_chunk(
-        [-1, 3],
-        Chunk::from(
-            [-1, 3],
-            [
-                0b0000_0000,
-                0b0000_0001,
-                0b0000_0110,
-                0b1000_0110,
-                0b0100_0110,
-                0b0110_0001,
-                0b0100_0000,
-                0b1000_0000,
-            ],
-            10.0,
-            (0.0, 0.0),
-        ),
-    );
-    game.insert_chunk(
-        [-2, 2],
-        Chunk::from(
-            [-2, 2],
-            [
-                0b0000_0110,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-            ],
-            10.0,
-            (0.0, 0.0),
-        ),
-    );
-    game.insert_chunk(
-        [0, 3],
-        Chunk::from(
-            [0, 3],
-            [
-                0b0100_0000,
-                0b0100_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0100_0000,
-                0b0100_0000,
-                0b0000_0000,
-            ],
-            10.0,
-            (0.0, 0.0),
-        ),
-    );
-    game.insert_chunk(
-        [1, 3],
-        Chunk::from(
-            [1, 3],
-            [
-                0b0000_0000,
-                0b0000_0000,
-                0b0001_1000,
-                0b0001_1000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-                0b0000_0000,
-            ],
-            10.0,
-            (0.0, 0.0),
-        ),
-    );
+    let mut view = View::new();
     let mut last_pos = Point2::new(0.0f32, 0.0f32);
-    let mut sel_pos = Point2::new(0.0f32, 0.0f32);
     let mut run: bool = false;
-    game.draw(&mut window);
+    // The corner a selection is being dragged from.
+    let mut selecting: Option<(i32, i32)> = None;
+    view.draw(&game, &mut window);
     while window.render_with(None, Some(&mut camera), None) {
         for event in window.events().iter() {
             match event.value {
@@ -158,17 +177,32 @@ fn main() {
                     let window_size =
                         Vector2::new(window.size()[0] as f32, window.size()[1] as f32);
                     last_pos = Point2::new(x as f32, y as f32);
-                    sel_pos = camera.unproject(&last_pos, &window_size);
-                    game.hover(sel_pos)
+                    let sel_pos = camera.unproject(&last_pos, &window_size);
+                    game.hover((sel_pos.x, sel_pos.y));
+                    if let Some(anchor) = selecting {
+                        game.select(anchor, game.cursor);
+                    } else {
+                        game.drag((sel_pos.x, sel_pos.y));
+                    }
                 }
-                WindowEvent::MouseButton(button, Action::Press, modif) => {
-                    // println!("mouse press event on {:?} with {:?}", button, modif);
+                WindowEvent::MouseButton(_button, Action::Press, modif) => {
                     let window_size =
                         Vector2::new(window.size()[0] as f32, window.size()[1] as f32);
-                    sel_pos = camera.unproject(&last_pos, &window_size);
-                    // println!("{:?}", sel_pos);
-                    game.click(sel_pos, button, modif);
-                    game.draw(&mut window);
+                    let sel_pos = camera.unproject(&last_pos, &window_size);
+                    if modif.contains(kiss3d::event::Modifiers::Shift) {
+                        // Drag out a selection
+                        let cell = game.cell_at((sel_pos.x, sel_pos.y));
+                        selecting = Some(cell);
+                        game.select(cell, cell);
+                    } else if !game.press((sel_pos.x, sel_pos.y)) {
+                        println!("can't fill a region that isn't enclosed");
+                    }
+                    game.sync();
+                    view.draw(&game, &mut window);
+                }
+                WindowEvent::MouseButton(_button, Action::Release, _modif) => {
+                    selecting = None;
+                    game.release();
                 }
                 WindowEvent::Key(key, action, modif) => {
                     println!("key event {:?} on {:?} with {:?}", key, action, modif);
@@ -177,18 +211,113 @@ fn main() {
                         && action == kiss3d::event::Action::Release
                         && modif == kiss3d::event::Modifiers::Control
                     {
+                        // A whole run is undone at once
+                        if !run {
+                            game.checkpoint();
+                        }
                         run = !run;
                     } else if key == kiss3d::event::Key::Space
                         && action == kiss3d::event::Action::Release
                     {
                         run = false;
-                        game.iterate(&mut window);
+                        game.checkpoint();
+                        game.iterate();
+                    }
+                    if key == kiss3d::event::Key::B && action == kiss3d::event::Action::Press {
+                        // Previous generation, or ten back with Shift
+                        run = false;
+                        let back = if modif == kiss3d::event::Modifiers::Shift {
+                            10
+                        } else {
+                            1
+                        };
+                        if game.step_back(back) {
+                            println!("generation {}", game.universe.generation);
+                        } else {
+                            println!("no earlier generation retained");
+                        }
+                    }
+                    if let Some(n) = SCRUB_KEYS.iter().position(|k| *k == key) {
+                        if action == kiss3d::event::Action::Release && modif.is_empty() {
+                            // Scrub through the retained generations: 1 is the
+                            // oldest and 0 the newest
+                            let generations = game.rewind.generations();
+                            if let (Some(first), Some(last)) =
+                                (generations.first(), generations.last())
+                            {
+                                let last = (*last).max(game.universe.generation);
+                                let target = first + (last - first) * n as u64 / 9;
+                                run = false;
+                                game.rewind_to(target);
+                                println!("generation {}", game.universe.generation);
+                            }
+                        }
+                    }
+                    // Engine and HashLife jumps
+                    if key == kiss3d::event::Key::H && action == kiss3d::event::Action::Release {
+                        if !game.toggle_engine() {
+                            println!("HashLife can't run this universe");
+                        }
+                        println!("engine: {:?}", game.engine_in_use());
+                    }
+                    if key == kiss3d::event::Key::J && action == kiss3d::event::Action::Release {
+                        run = false;
+                        game.checkpoint();
+                        let wanted = 1u64 << game.jump_exponent;
+                        let made = game.jump();
+                        if made < wanted {
+                            println!(
+                                "jumped {} generations, as far as the universe reaches",
+                                made
+                            );
+                        }
+                        println!("generation {}", game.generation());
+                    }
+                    if key == kiss3d::event::Key::Period
+                        && action == kiss3d::event::Action::Press
+                        && game.jump_exponent < 60
+                    {
+                        game.jump_exponent += 1;
+                        println!("jump: 2^{} generations", game.jump_exponent);
+                    }
+                    if key == kiss3d::event::Key::Comma
+                        && action == kiss3d::event::Action::Press
+                        && game.jump_exponent > 0
+                    {
+                        game.jump_exponent -= 1;
+                        println!("jump: 2^{} generations", game.jump_exponent);
+                    }
+                    if key == kiss3d::event::Key::C
+                        && action == kiss3d::event::Action::Release
+                        && modif.is_empty()
+                    {
+                        // Colour painted by clicks under Immigration and QuadLife
+                        game.next_color();
+                        println!("colour {}", game.color);
+                    }
+                    // Drawing tools
+                    let tool = match key {
+                        kiss3d::event::Key::T => Some(Tool::Toggle),
+                        kiss3d::event::Key::P => Some(Tool::Pencil),
+                        kiss3d::event::Key::L => Some(Tool::Line),
+                        kiss3d::event::Key::E
+                            if modif.contains(kiss3d::event::Modifiers::Shift) =>
+                        {
+                            Some(Tool::FilledRectangle)
+                        }
+                        kiss3d::event::Key::E => Some(Tool::Rectangle),
+                        kiss3d::event::Key::G => Some(Tool::Fill),
+                        _ => None,
+                    };
+                    if let Some(tool) = tool {
+                        if action == kiss3d::event::Action::Release {
+                            game.tool = tool;
+                            println!("tool: {}", tool);
+                        }
                     }
                     if key == kiss3d::event::Key::Back && action == kiss3d::event::Action::Release {
                         // Backspace
-                        for chunk in game.chunks().into_iter() {
-                            chunk.set([0; 8]);
-                        }
+                        game.clear();
                     }
                     // Zooming
                     if key == kiss3d::event::Key::Equals && action == kiss3d::event::Action::Press {
@@ -238,42 +367,306 @@ fn main() {
                     }
                     // Ctrl Commands
                     if key == kiss3d::event::Key::S
+                        && action == kiss3d::event::Action::Release
+                        && modif.contains(kiss3d::event::Modifiers::Control)
+                    {
+                        // Save, or Save As with Shift
+                        let path = if modif.contains(kiss3d::event::Modifiers::Shift) {
+                            None
+                        } else {
+                            game.path.clone()
+                        };
+                        if let Some(path) = path.or_else(save_dialog) {
+                            match game.save(&path) {
+                                Ok(()) => recent.push(&path),
+                                Err(e) => println!("{}", e),
+                            }
+                        }
+                    }
+                    if key == kiss3d::event::Key::Z
+                        && action == kiss3d::event::Action::Press
+                        && modif == kiss3d::event::Modifiers::Control
+                    {
+                        // Undo
+                        run = false;
+                        if !game.undo() {
+                            println!("nothing to undo");
+                        }
+                    }
+                    if key == kiss3d::event::Key::Y
+                        && action == kiss3d::event::Action::Press
+                        && modif == kiss3d::event::Modifiers::Control
+                    {
+                        // Redo
+                        run = false;
+                        if !game.redo() {
+                            println!("nothing to redo");
+                        }
+                    }
+                    if key == kiss3d::event::Key::C
+                        && action == kiss3d::event::Action::Release
+                        && modif == kiss3d::event::Modifiers::Control
+                    {
+                        // Copy the selection
+                        if let Some(text) = game.copy() {
+                            copy_to_clipboard(&text);
+                        }
+                    }
+                    if key == kiss3d::event::Key::X
+                        && action == kiss3d::event::Action::Release
+                        && modif == kiss3d::event::Modifiers::Control
+                    {
+                        // Cut the selection
+                        if let Some(text) = game.cut() {
+                            copy_to_clipboard(&text);
+                        }
+                    }
+                    if key == kiss3d::event::Key::V
                         && action == kiss3d::event::Action::Release
                         && modif == kiss3d::event::Modifiers::Control
                     {
-                        // Save
-                        game.save();
+                        // Paste RLE from other programs, or else what was copied
+                        // here, following the cursor until a click places it
+                        let pasted = match paste_from_clipboard() {
+                            Some(text) if game.paste(&text).is_ok() => Ok(()),
+                            _ => match game.clipboard.clone() {
+                                Some(text) => game.paste(&text),
+                                None => {
+                                    println!("nothing to paste");
+                                    Ok(())
+                                }
+                            },
+                        };
+                        if let Err(e) = pasted {
+                            println!("can't paste: {}", e);
+                        }
+                    }
+                    if key == kiss3d::event::Key::Delete && action == kiss3d::event::Action::Release
+                    {
+                        // Clear inside the selection, or outside it with Shift
+                        if modif.contains(kiss3d::event::Modifiers::Shift) {
+                            game.clear_outside();
+                        } else {
+                            game.clear_selection();
+                        }
+                    }
+                    if key == kiss3d::event::Key::R && action == kiss3d::event::Action::Release {
+                        // Turn the paste or selection clockwise, half way with
+                        // Ctrl, or anticlockwise with Shift
+                        let transform = if modif.contains(kiss3d::event::Modifiers::Control) {
+                            Transform::Rotate180
+                        } else if modif.contains(kiss3d::event::Modifiers::Shift) {
+                            Transform::Rotate270
+                        } else {
+                            Transform::Rotate90
+                        };
+                        game.transform(transform);
+                    }
+                    if key == kiss3d::event::Key::F && action == kiss3d::event::Action::Release {
+                        // Mirror the paste or selection left to right, or top
+                        // to bottom with Shift
+                        let transform = if modif.contains(kiss3d::event::Modifiers::Shift) {
+                            Transform::FlipVertical
+                        } else {
+                            Transform::FlipHorizontal
+                        };
+                        game.transform(transform);
+                    }
+                    if key == kiss3d::event::Key::N && action == kiss3d::event::Action::Release {
+                        if modif.contains(kiss3d::event::Modifiers::Shift) {
+                            // Next soup symmetry
+                            let next = Symmetry::ALL.iter().position(|s| *s == symmetry);
+                            let next = next.map_or(0, |i| (i + 1) % Symmetry::ALL.len());
+                            symmetry = Symmetry::ALL[next];
+                            println!("soup symmetry {}", symmetry);
+                        } else {
+                            // A random soup in the selection, or a 16x16 one at
+                            // the cursor
+                            let (x, y) = game.cursor;
+                            let region = game
+                                .selection
+                                .unwrap_or_else(|| Selection::new((x, y), (x + 15, y + 15)));
+                            game.soup(region, density, symmetry, seed);
+                            println!("soup {} seed {} density {}", symmetry, seed, density);
+                            seed = seed.wrapping_add(1);
+                        }
+                    }
+                    if (key == kiss3d::event::Key::LBracket || key == kiss3d::event::Key::RBracket)
+                        && action == kiss3d::event::Action::Release
+                    {
+                        // Browse the built-in patterns, pasting each in turn
+                        let step = if key == kiss3d::event::Key::RBracket {
+                            1
+                        } else {
+                            -1
+                        };
+                        println!("pattern: {}", game.browse(step));
+                    }
+                    if key == kiss3d::event::Key::Escape && action == kiss3d::event::Action::Release
+                    {
+                        // Drop a paste, or else the selection
+                        if game.pasting.is_some() {
+                            game.cancel_paste();
+                        } else {
+                            game.deselect();
+                        }
                     }
                     if key == kiss3d::event::Key::O
                         && action == kiss3d::event::Action::Release
                         && modif == kiss3d::event::Modifiers::Control
                     {
                         // Open
-                        match game.open(&mut window) {
-                            Ok(t) => {}
-                            Err(e) => {
-                                println!("{:?}", e)
+                        if let Some(path) = open_dialog() {
+                            open(&mut game, &mut recent, &path);
+                        }
+                    }
+                    if let Some(n) = RECENT_KEYS.iter().position(|k| *k == key) {
+                        if action == kiss3d::event::Action::Release
+                            && modif == kiss3d::event::Modifiers::Control
+                        {
+                            // Open a recent file
+                            if let Some(path) = recent.get(n).map(Path::to_path_buf) {
+                                open(&mut game, &mut recent, &path);
                             }
                         }
                     }
                 }
-                WindowEvent::CursorPos(x, y, _modif) => {
-                    last_pos = Point2::new(x as f32, y as f32);
-                }
                 WindowEvent::Close => {
                     //save
                     println!("closing");
                 }
-                WindowEvent::Touch(var1, x, y, touchAction, modif) => {
-                    println!("{} {} {} {:?} {:?}", var1, x, y, touchAction, modif);
+                WindowEvent::Touch(id, x, y, touch_action, modif) => {
+                    println!("{} {} {} {:?} {:?}", id, x, y, touch_action, modif);
                 }
                 _ => {}
             }
         }
-        game.draw(&mut window);
+        // HashLife leaves the universe behind until it is needed
+        game.sync();
+        view.draw(&game, &mut window);
         if run {
-            game.iterate(&mut window);
-            // sleep(Duration::from_millis(100)); // write a better alternative
+            game.iterate();
+        }
+    }
+}
+
+fn open(game: &mut Game, recent: &mut RecentFiles, path: &Path) {
+    match game.open(path) {
+        Ok(()) => recent.push(path),
+        Err(e) => println!("{}: {}", path.display(), e),
+    }
+}
+
+// Files saved without a known extension get the native .cgl format.
+fn with_extension(path: PathBuf) -> PathBuf {
+    match FileFormat::from_path(&path) {
+        Some(_) => path,
+        None => path.with_extension("cgl"),
+    }
+}
+
+// The system clipboard goes through whichever of the usual command line tools
+// is installed. Copied patterns are printed when there is none.
+const COPY_COMMANDS: [(&str, &[&str]); 5] = [
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip", &[]),
+];
+const PASTE_COMMANDS: [(&str, &[&str]); 5] = [
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    ("pbpaste", &[]),
+    ("powershell", &["-command", "Get-Clipboard"]),
+];
+
+fn copy_to_clipboard(text: &str) {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    for (program, args) in COPY_COMMANDS.iter() {
+        let child = Command::new(program)
+            .args(args.iter())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = child {
+            let written = match child.stdin.take() {
+                Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+                None => false,
+            };
+            if written && child.wait().is_ok_and(|status| status.success()) {
+                return;
+            }
         }
     }
+    print!("{}", text);
+}
+
+fn paste_from_clipboard() -> Option<String> {
+    use std::process::{Command, Stdio};
+    PASTE_COMMANDS.iter().find_map(|(program, args)| {
+        let output = Command::new(program)
+            .args(args.iter())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if output.status.success() {
+            String::from_utf8(output.stdout).ok()
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(feature = "dialogs")]
+fn open_dialog() -> Option<PathBuf> {
+    match nfd::open_file_dialog(Some(FileFormat::FILTER), None) {
+        Ok(nfd::Response::Okay(path)) => Some(PathBuf::from(path)),
+        Ok(_) => None,
+        Err(e) => {
+            println!("{}", e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "dialogs")]
+fn save_dialog() -> Option<PathBuf> {
+    match nfd::open_save_dialog(Some(FileFormat::FILTER), None) {
+        Ok(nfd::Response::Okay(path)) => Some(with_extension(PathBuf::from(path))),
+        Ok(_) => None,
+        Err(e) => {
+            println!("{}", e);
+            None
+        }
+    }
+}
+
+// Without native dialogs the path is read from the terminal.
+#[cfg(not(feature = "dialogs"))]
+fn open_dialog() -> Option<PathBuf> {
+    prompt("Open file: ")
+}
+
+#[cfg(not(feature = "dialogs"))]
+fn save_dialog() -> Option<PathBuf> {
+    prompt("Save as: ").map(with_extension)
+}
+
+#[cfg(not(feature = "dialogs"))]
+fn prompt(message: &str) -> Option<PathBuf> {
+    use std::io::Write;
+    print!("{}", message);
+    std::io::stdout().flush().ok()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).ok()?;
+    let line = line.trim();
+    if line.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(line))
+    }
 }
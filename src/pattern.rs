@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use crate::rule::Rule;
+use crate::table::RuleTable;
+
+// Rotations (clockwise, on screen) and mirror images of a pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    // Mirrored left to right.
+    FlipHorizontal,
+    // Mirrored top to bottom.
+    FlipVertical,
+    // Mirrored in the diagonal from the top left, swapping x and y.
+    FlipDiagonal,
+    // Mirrored in the diagonal from the top right.
+    FlipAntidiagonal,
+}
+impl Transform {
+    // Where the cell (x, y) of a width by height box with its top left at
+    // (0, 0) ends up. A quarter turn swaps the width and height.
+    pub fn apply(self, (x, y): (i32, i32), (width, height): (i32, i32)) -> (i32, i32) {
+        match self {
+            Transform::Identity => (x, y),
+            Transform::Rotate90 => (height - 1 - y, x),
+            Transform::Rotate180 => (width - 1 - x, height - 1 - y),
+            Transform::Rotate270 => (y, width - 1 - x),
+            Transform::FlipHorizontal => (width - 1 - x, y),
+            Transform::FlipVertical => (x, height - 1 - y),
+            Transform::FlipDiagonal => (y, x),
+            Transform::FlipAntidiagonal => (height - 1 - y, width - 1 - x),
+        }
+    }
+    pub fn swaps_sides(self) -> bool {
+        matches!(
+            self,
+            Transform::Rotate90
+                | Transform::Rotate270
+                | Transform::FlipDiagonal
+                | Transform::FlipAntidiagonal
+        )
+    }
+}
+
+// A set of live cells in absolute coordinates (x right, y down), plus the
+// metadata pattern files carry alongside them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    pub cells: Vec<(i32, i32)>,
+    // Cells in states above 1 under a multi-state rule, with their state.
+    pub states: Vec<(i32, i32, u8)>,
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<Rule>,
+    // The rule table the pattern runs under, in place of `rule`.
+    pub table: Option<Arc<RuleTable>>,
+}
+impl Pattern {
+    pub fn new() -> Pattern {
+        Pattern::default()
+    }
+    pub fn from_cells(cells: Vec<(i32, i32)>) -> Pattern {
+        let mut pattern = Pattern {
+            cells,
+            ..Pattern::default()
+        };
+        pattern.sort();
+        pattern
+    }
+    // Sorts cells row by row and drops duplicates.
+    pub fn sort(&mut self) {
+        self.cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        self.cells.dedup();
+        self.states.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        self.states.dedup_by_key(|&mut (x, y, _)| (x, y));
+    }
+    // Every cell that isn't dead with its state, row by row. A coloured cell
+    // is also listed as live, so the higher state wins.
+    pub fn all_states(&self) -> Vec<(i32, i32, u8)> {
+        let mut cells: Vec<(i32, i32, u8)> = self
+            .cells
+            .iter()
+            .map(|&(x, y)| (x, y, 1))
+            .chain(self.states.iter().copied())
+            .collect();
+        cells.sort_unstable_by_key(|&(x, y, state)| (y, x, std::cmp::Reverse(state)));
+        cells.dedup_by_key(|&mut (x, y, _)| (x, y));
+        cells
+    }
+    // (min_x, min_y, max_x, max_y) of the cells in any state but dead.
+    pub fn bounding_box(&self) -> Option<(i32, i32, i32, i32)> {
+        let mut cells = self
+            .cells
+            .iter()
+            .copied()
+            .chain(self.states.iter().map(|&(x, y, _)| (x, y)));
+        let first = cells.next()?;
+        let mut bounds = (first.0, first.1, first.0, first.1);
+        for (x, y) in cells {
+            bounds.0 = bounds.0.min(x);
+            bounds.1 = bounds.1.min(y);
+            bounds.2 = bounds.2.max(x);
+            bounds.3 = bounds.3.max(y);
+        }
+        Some(bounds)
+    }
+    pub fn translate(&mut self, offset: (i32, i32)) {
+        for cell in self.cells.iter_mut() {
+            cell.0 += offset.0;
+            cell.1 += offset.1;
+        }
+        for cell in self.states.iter_mut() {
+            cell.0 += offset.0;
+            cell.1 += offset.1;
+        }
+    }
+    // Transforms the cells within a width by height box with its top left at
+    // (0, 0).
+    pub fn transform_within(&mut self, transform: Transform, size: (i32, i32)) {
+        for cell in self.cells.iter_mut() {
+            *cell = transform.apply(*cell, size);
+        }
+        for cell in self.states.iter_mut() {
+            let (x, y) = transform.apply((cell.0, cell.1), size);
+            *cell = (x, y, cell.2);
+        }
+        self.sort();
+    }
+    // Transforms the pattern in place, keeping the top left of its bounding
+    // box where it was.
+    pub fn transform(&mut self, transform: Transform) {
+        if let Some((x, y, right, bottom)) = self.bounding_box() {
+            self.translate((-x, -y));
+            self.transform_within(transform, (right - x + 1, bottom - y + 1));
+            self.translate((x, y));
+        }
+    }
+    // Moves the pattern so the top left of its bounding box is at (0, 0).
+    pub fn normalize(&mut self) {
+        if let Some((x, y, _, _)) = self.bounding_box() {
+            self.translate((-x, -y));
+        }
+        self.sort();
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+use crate::pattern::Pattern;
+
+// Reader and writer for the plaintext (.cells) pattern format:
+//
+//     !Name: Glider
+//     .O.
+//     ..O
+//     OOO
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaintextError {
+    UnexpectedChar(char, usize),
+}
+impl fmt::Display for PlaintextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaintextError::UnexpectedChar(c, line) => {
+                write!(
+                    f,
+                    "unexpected {:?} on line {} of plaintext pattern",
+                    c, line
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for PlaintextError {}
+
+pub fn read(text: &str) -> Result<Pattern, PlaintextError> {
+    let mut pattern = Pattern::new();
+    let mut y = 0;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | '*' => pattern.cells.push((x as i32, y)),
+                _ => return Err(PlaintextError::UnexpectedChar(c, n + 1)),
+            }
+        }
+        y += 1;
+    }
+    pattern.sort();
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in pattern.comments.iter() {
+        out.push_str(&format!("!{}\n", comment));
+    }
+    let mut cells = pattern.cells.clone();
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    let (min_x, min_y, _, max_y) = match pattern.bounding_box() {
+        Some(bounds) => bounds,
+        None => return out,
+    };
+    let mut cells = cells.into_iter().peekable();
+    for y in min_y..=max_y {
+        let mut line = String::new();
+        while let Some(&(x, _)) = cells.peek().filter(|cell| cell.1 == y) {
+            let col = (x - min_x) as usize;
+            while line.len() < col {
+                line.push('.');
+            }
+            line.push('O');
+            cells.next();
+        }
+        if line.is_empty() {
+            line.push('.');
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+
+use std::sync::Arc;
+
+use crate::pattern::Pattern;
+use crate::rule::ParseRuleError;
+use crate::rule::Rule;
+use crate::table::RuleTable;
+
+// Reader and writer for the run-length encoded pattern format:
+//
+//     #N Glider
+//     x = 3, y = 3, rule = B3/S23
+//     bob$2bo$3o!
+//
+// Multi-state patterns use '.' for dead cells, 'A' to 'X' for states 1 to 24
+// and a 'p' to 'y' prefix for each further 24 states, so "pA" is state 25.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RleError {
+    MissingHeader,
+    InvalidHeader(String),
+    InvalidRule(ParseRuleError),
+    UnexpectedChar(char, usize),
+    // A run on this line reaches past the header's width or height.
+    OutOfBounds(usize),
+    // A state the pattern's rule doesn't have, and the line it is on.
+    InvalidState(u8, usize),
+    MissingEnd,
+}
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RleError::MissingHeader => write!(f, "RLE is missing its \"x = .., y = ..\" line"),
+            RleError::InvalidHeader(s) => write!(f, "invalid RLE header {:?}", s),
+            RleError::InvalidRule(e) => write!(f, "invalid RLE rule: {}", e),
+            RleError::UnexpectedChar(c, line) => {
+                write!(f, "unexpected {:?} on line {} of RLE", c, line)
+            }
+            RleError::OutOfBounds(line) => {
+                write!(f, "run on line {} of RLE goes outside its x and y", line)
+            }
+            RleError::InvalidState(state, line) => {
+                write!(
+                    f,
+                    "state {} on line {} of RLE is not in its rule",
+                    state, line
+                )
+            }
+            RleError::MissingEnd => write!(f, "RLE ends without a '!'"),
+        }
+    }
+}
+impl std::error::Error for RleError {}
+impl From<ParseRuleError> for RleError {
+    fn from(e: ParseRuleError) -> RleError {
+        RleError::InvalidRule(e)
+    }
+}
+
+const LINE_LENGTH: usize = 70;
+
+pub fn read(text: &str) -> Result<Pattern, RleError> {
+    read_with(text, &[])
+}
+// Reads a pattern whose rule may also name one of `tables`, or a built-in
+// rule table.
+pub fn read_with(text: &str, tables: &[Arc<RuleTable>]) -> Result<Pattern, RleError> {
+    let mut pattern = Pattern::new();
+    let mut header = None;
+    let mut ended = false;
+    let mut pos = (0, 0);
+    let mut run: i32 = 0;
+    let mut prefix: Option<u8> = None;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if ended || line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let text = chars.as_str().trim().to_string();
+            match kind {
+                Some('N') => pattern.name = Some(text),
+                Some('r') => read_rule(&text, tables, &mut pattern)?,
+                _ => pattern.comments.push(text),
+            }
+            continue;
+        }
+        let (width, height) = match header {
+            Some(size) => size,
+            None => {
+                header = Some(read_header(line, tables, &mut pattern)?);
+                continue;
+            }
+        };
+        // Where a run of `run` cells starting at `start` ends, or an error
+        // when it passes `limit`.
+        let advance = |start: i32, run: i32, limit: i32| {
+            start
+                .checked_add(run.max(1))
+                .filter(|&end| end <= limit)
+                .ok_or(RleError::OutOfBounds(n + 1))
+        };
+        for c in line.chars() {
+            if prefix.is_some() && !c.is_ascii_uppercase() {
+                return Err(RleError::UnexpectedChar(c, n + 1));
+            }
+            match c {
+                '0'..='9' => {
+                    run = run
+                        .checked_mul(10)
+                        .and_then(|r| r.checked_add(c as i32 - '0' as i32))
+                        .ok_or(RleError::UnexpectedChar(c, n + 1))?;
+                    continue;
+                }
+                'b' | '.' => pos.0 = advance(pos.0, run, width)?,
+                '$' => {
+                    pos.0 = 0;
+                    pos.1 = advance(pos.1, run, height)?;
+                }
+                '!' => {
+                    ended = true;
+                    break;
+                }
+                'p'..='y' if states(&pattern) > 2 => {
+                    prefix = Some(c as u8 - b'p' + 1);
+                    continue;
+                }
+                'o' | 'A'..='X' => {
+                    let state = match (c, prefix.take()) {
+                        ('o', None) => 1,
+                        ('A'..='X', prefix) if states(&pattern) > 2 => {
+                            let state =
+                                prefix.unwrap_or(0) as u32 * 24 + (c as u32 - 'A' as u32) + 1;
+                            if state > u8::MAX as u32 {
+                                return Err(RleError::UnexpectedChar(c, n + 1));
+                            }
+                            if state >= states(&pattern) as u32 {
+                                return Err(RleError::InvalidState(state as u8, n + 1));
+                            }
+                            state as u8
+                        }
+                        _ => return Err(RleError::UnexpectedChar(c, n + 1)),
+                    };
+                    let end = advance(pos.0, run, width)?;
+                    if pos.1 >= height {
+                        return Err(RleError::OutOfBounds(n + 1));
+                    }
+                    for x in pos.0..end {
+                        if state == 1 {
+                            pattern.cells.push((x, pos.1));
+                        } else {
+                            pattern.states.push((x, pos.1, state));
+                        }
+                    }
+                    pos.0 = end;
+                }
+                c if c.is_whitespace() => continue,
+                _ => return Err(RleError::UnexpectedChar(c, n + 1)),
+            }
+            run = 0;
+        }
+    }
+    if header.is_none() {
+        return Err(RleError::MissingHeader);
+    }
+    if !ended {
+        return Err(RleError::MissingEnd);
+    }
+    pattern.sort();
+    Ok(pattern)
+}
+
+fn push(runs: &mut Vec<(i32, String)>, count: i32, tag: &str) {
+    if count <= 0 {
+        return;
+    }
+    match runs.last_mut() {
+        Some(last) if last.1 == tag => last.0 += count,
+        _ => runs.push((count, tag.to_string())),
+    }
+}
+
+// How many states the pattern's rule or rule table gives cells. Any past
+// live and dead are read from the letters 'A' to 'X' and their prefixes
+// rather than 'o'.
+fn states(pattern: &Pattern) -> u8 {
+    let rule = pattern.rule.unwrap_or_default();
+    match &pattern.table {
+        Some(table) => table.states,
+        None if rule.is_colored() => rule.colors + 1,
+        None => rule.states,
+    }
+}
+
+// The tag of a live cell in `state`, where `multi` picks the multi-state
+// letters over "o".
+fn state_tag(state: u8, multi: bool) -> String {
+    if !multi {
+        return "o".to_string();
+    }
+    let (prefix, letter) = ((state - 1) / 24, (state - 1) % 24);
+    let mut tag = String::new();
+    if prefix > 0 {
+        tag.push((b'p' + prefix - 1) as char);
+    }
+    tag.push((b'A' + letter) as char);
+    tag
+}
+
+fn read_rule(
+    value: &str,
+    tables: &[Arc<RuleTable>],
+    pattern: &mut Pattern,
+) -> Result<(), RleError> {
+    match value.parse::<Rule>() {
+        Ok(rule) => {
+            pattern.rule = Some(rule);
+            pattern.table = None;
+        }
+        Err(e) => {
+            let table = tables
+                .iter()
+                .find(|table| table.name.eq_ignore_ascii_case(value))
+                .cloned()
+                .or_else(|| RuleTable::builtin(value).map(Arc::new))
+                .ok_or(e)?;
+            pattern.rule = None;
+            pattern.table = Some(table);
+        }
+    }
+    Ok(())
+}
+
+// Reads the header's fields into `pattern`, returning its width and height.
+fn read_header(
+    line: &str,
+    tables: &[Arc<RuleTable>],
+    pattern: &mut Pattern,
+) -> Result<(i32, i32), RleError> {
+    // the rule comes last and may itself contain commas
+    let (sizes, rule) = match line.find("rule") {
+        Some(i) => (line[..i].trim_end().trim_end_matches(','), Some(&line[i..])),
+        None => (line, None),
+    };
+    let mut size = (None, None);
+    for field in sizes.split(',').chain(rule) {
+        let mut parts = field.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = match parts.next() {
+            Some(value) => value.trim(),
+            None => return Err(RleError::InvalidHeader(line.to_string())),
+        };
+        match key {
+            "x" | "y" => {
+                let value = match value.parse::<i32>() {
+                    Ok(value) if value >= 0 => value,
+                    _ => return Err(RleError::InvalidHeader(line.to_string())),
+                };
+                if key == "x" {
+                    size.0 = Some(value);
+                } else {
+                    size.1 = Some(value);
+                }
+            }
+            "rule" => read_rule(value, tables, pattern)?,
+            _ => {}
+        }
+    }
+    match size {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(RleError::MissingHeader),
+    }
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {}\n", name));
+    }
+    for comment in pattern.comments.iter() {
+        out.push_str(&format!("#C {}\n", comment));
+    }
+    let cells = pattern.all_states();
+    let multi = !pattern.states.is_empty()
+        || pattern.table.as_ref().is_some_and(|table| table.states > 2)
+        || pattern
+            .rule
+            .is_some_and(|r| r.is_generations() || r.is_colored());
+    let dead = if multi { "." } else { "b" };
+    let (min_x, min_y, max_x, max_y) = pattern.bounding_box().unwrap_or((0, 0, -1, -1));
+    let rule = match &pattern.table {
+        Some(table) => table.name.clone(),
+        None => pattern.rule.unwrap_or_default().to_string(),
+    };
+    out.push_str(&format!(
+        "x = {}, y = {}, rule = {}\n",
+        max_x - min_x + 1,
+        max_y - min_y + 1,
+        rule
+    ));
+
+    // Runs of (count, tag), with trailing dead cells on each row dropped.
+    let mut runs: Vec<(i32, String)> = Vec::new();
+    let mut row = min_y;
+    let mut col = min_x;
+    for &(x, y, state) in cells.iter() {
+        if y != row {
+            push(&mut runs, y - row, "$");
+            row = y;
+            col = min_x;
+        }
+        push(&mut runs, x - col, dead);
+        push(&mut runs, 1, &state_tag(state, multi));
+        col = x + 1;
+    }
+    runs.push((1, "!".to_string()));
+
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let item = if count == 1 {
+            tag
+        } else {
+            format!("{}{}", count, tag)
+        };
+        if line.len() + item.len() > LINE_LENGTH {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        line.push_str(&item);
+    }
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
@@ -0,0 +1,684 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::SIZE;
+
+// Outer-totalistic birth/survival rule. Bit n of `birth`/`survival` is set
+// when a cell with n live neighbours (self excluded) is born/survives.
+//
+// Isotropic non-totalistic rules also look at where the neighbours are, and
+// keep the full table in `configurations`. Their `birth`/`survival` bits are
+// only set for counts where every configuration is born/survives.
+//
+// With more than two `states` it is a Generations rule: a live cell that does
+// not survive passes through the dying states 2, 3, .. states - 1 before it
+// is dead again. Only live cells (state 1) count as neighbours, and only dead
+// ones (state 0) can be born.
+//
+// The neighbours are the eight surrounding cells unless `neighbourhood` says
+// otherwise, or the cells within a larger radius for a Larger than Life
+// `range` rule, which replaces `birth` and `survival` with count ranges.
+//
+// With more than one of `colors`, as in Immigration and QuadLife, every live
+// cell also has a colour. Survivors keep theirs and births take the colour
+// `birth_color` picks from their parents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    pub birth: u16,
+    pub survival: u16,
+    pub states: u8,
+    pub configurations: Option<Configurations>,
+    pub neighbourhood: Neighbourhood,
+    pub range: Option<LargerThanLife>,
+    pub colors: u8,
+}
+impl Rule {
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+        states: 2,
+        configurations: None,
+        neighbourhood: Neighbourhood::Moore,
+        range: None,
+        colors: 1,
+    };
+    // Conway's rules with two and four colours.
+    pub const IMMIGRATION: Rule = Rule {
+        colors: 2,
+        ..Rule::CONWAY
+    };
+    pub const QUADLIFE: Rule = Rule {
+        colors: 4,
+        ..Rule::CONWAY
+    };
+    pub fn new(birth: &[u8], survival: &[u8]) -> Rule {
+        let mut rule = Rule {
+            birth: 0,
+            survival: 0,
+            states: 2,
+            configurations: None,
+            neighbourhood: Neighbourhood::Moore,
+            range: None,
+            colors: 1,
+        };
+        for &n in birth {
+            rule.birth |= 1 << n;
+        }
+        for &n in survival {
+            rule.survival |= 1 << n;
+        }
+        rule
+    }
+    pub fn generations(birth: &[u8], survival: &[u8], states: u8) -> Rule {
+        Rule {
+            states,
+            ..Rule::new(birth, survival)
+        }
+    }
+    pub fn is_generations(&self) -> bool {
+        self.states > 2
+    }
+    pub fn is_colored(&self) -> bool {
+        self.colors > 1
+    }
+    // How far away a cell's neighbours can be.
+    pub fn radius(&self) -> usize {
+        self.range.map_or(1, |range| range.radius as usize)
+    }
+    // Bits needed to store every state number up to and including `states`,
+    // or every colour number below `colors`.
+    pub fn state_bits(&self) -> usize {
+        if self.is_generations() {
+            8 - self.states.leading_zeros() as usize
+        } else if self.is_colored() {
+            8 - (self.colors - 1).leading_zeros() as usize
+        } else {
+            0
+        }
+    }
+    // The colour of a cell born to parents of which `counts[c]` have colour
+    // c: the one most of them have, or when no colour leads, the first one
+    // none of them have. With three parents of different colours that is
+    // QuadLife's fourth colour.
+    pub fn birth_color(&self, counts: &[u8]) -> u8 {
+        let most = counts.iter().copied().max().unwrap_or(0);
+        let first = |n: u8| counts.iter().position(|&count| count == n);
+        let color = if counts.iter().filter(|&&count| count == most).count() == 1 {
+            first(most)
+        } else {
+            first(0).or_else(|| first(most))
+        };
+        color.unwrap_or(0) as u8
+    }
+    pub fn born(&self, count: u8) -> bool {
+        self.birth & (1 << count) != 0
+    }
+    pub fn survives(&self, count: u8) -> bool {
+        self.survival & (1 << count) != 0
+    }
+    pub fn next_state(&self, alive: bool, count: u8) -> bool {
+        if alive {
+            self.survives(count)
+        } else {
+            self.born(count)
+        }
+    }
+    pub fn is_totalistic(&self) -> bool {
+        self.configurations.is_none()
+    }
+    pub fn born_in(&self, config: u8) -> bool {
+        match &self.configurations {
+            Some(table) => contains(&table.birth, config),
+            None => self.born((config & self.neighbourhood.mask()).count_ones() as u8),
+        }
+    }
+    pub fn survives_in(&self, config: u8) -> bool {
+        match &self.configurations {
+            Some(table) => contains(&table.survival, config),
+            None => self.survives((config & self.neighbourhood.mask()).count_ones() as u8),
+        }
+    }
+    // The next state of a cell whose live neighbours form `config`.
+    pub fn next_cell(&self, alive: bool, config: u8) -> bool {
+        if alive {
+            self.survives_in(config)
+        } else {
+            self.born_in(config)
+        }
+    }
+    // Counts where some configurations are born/survive but not all of them,
+    // which need the configuration itself looked up.
+    pub fn mixed(&self) -> (u16, u16) {
+        let mut mixed = (0, 0);
+        if let Some(table) = &self.configurations {
+            for config in 0..=255u8 {
+                let count = config.count_ones();
+                if contains(&table.birth, config) && !self.born(count as u8) {
+                    mixed.0 |= 1 << count;
+                }
+                if contains(&table.survival, config) && !self.survives(count as u8) {
+                    mixed.1 |= 1 << count;
+                }
+            }
+        }
+        mixed
+    }
+    // Builds a rule from the sets of configurations that are born and that
+    // survive, keeping the table only when some count is split by it.
+    fn from_configurations(birth: [u64; 4], survival: [u64; 4], states: u8) -> Rule {
+        let totals = |set: &[u64; 4]| {
+            let mut all: u16 = 0x1ff;
+            let mut any: u16 = 0;
+            for config in 0..=255u8 {
+                let count = config.count_ones();
+                if contains(set, config) {
+                    any |= 1 << count;
+                } else {
+                    all &= !(1 << count);
+                }
+            }
+            (all, any == all)
+        };
+        let (birth_counts, birth_totalistic) = totals(&birth);
+        let (survival_counts, survival_totalistic) = totals(&survival);
+        Rule {
+            birth: birth_counts,
+            survival: survival_counts,
+            states,
+            neighbourhood: Neighbourhood::Moore,
+            range: None,
+            colors: 1,
+            configurations: if birth_totalistic && survival_totalistic {
+                None
+            } else {
+                Some(Configurations { birth, survival })
+            },
+        }
+    }
+    // The set of configurations in a "23" or Hensel "2-a3ik" section.
+    fn parse_counts(s: &str) -> Result<[u64; 4], ParseRuleError> {
+        let letters = hensel_letters();
+        let mut set = [0; 4];
+        let mut seen: u16 = 0;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let n = match c.to_digit(10) {
+                Some(n) if n <= 8 => n,
+                _ => return Err(ParseRuleError::InvalidCount(c)),
+            };
+            if seen & (1 << n) != 0 {
+                return Err(ParseRuleError::DuplicateCount(c));
+            }
+            seen |= 1 << n;
+            let negated = chars.next_if_eq(&'-').is_some();
+            let mut chosen = String::new();
+            while let Some(letter) = chars.next_if(|c| c.is_ascii_lowercase()) {
+                if !HENSEL[n as usize].contains(letter) || chosen.contains(letter) {
+                    return Err(ParseRuleError::InvalidLetter(letter, n as u8));
+                }
+                chosen.push(letter);
+            }
+            if negated && chosen.is_empty() {
+                return Err(ParseRuleError::InvalidLetter('-', n as u8));
+            }
+            for config in 0..=255u8 {
+                let letter = letters[config as usize] as char;
+                if config.count_ones() == n
+                    && (chosen.is_empty() || chosen.contains(letter) != negated)
+                {
+                    set[config as usize / 64] |= 1 << (config % 64);
+                }
+            }
+        }
+        Ok(set)
+    }
+    fn parse_states(s: &str) -> Result<u8, ParseRuleError> {
+        match s.parse::<u8>() {
+            Ok(n) if n >= 2 => Ok(n),
+            _ => Err(ParseRuleError::InvalidStates(s.to_string())),
+        }
+    }
+    // A Larger than Life rule like "R5,C0,M1,S34..58,B34..45,NM". C and M
+    // default to 0 and N to M.
+    fn parse_range(s: &str) -> Result<Rule, ParseRuleError> {
+        let mut radius = None;
+        let mut states = 2;
+        let mut middle = false;
+        let mut survival = None;
+        let mut birth = None;
+        let mut neighbourhood = Neighbourhood::Moore;
+        for field in s.split(',') {
+            let field = field.trim();
+            let invalid = || ParseRuleError::InvalidRange(field.to_string());
+            let value = field.get(1..).ok_or_else(invalid)?;
+            let bounds = || -> Result<(u16, u16), ParseRuleError> {
+                let (min, max) = value.split_once("..").ok_or_else(invalid)?;
+                match (min.parse(), max.parse()) {
+                    (Ok(min), Ok(max)) if min <= max => Ok((min, max)),
+                    _ => Err(invalid()),
+                }
+            };
+            match field.chars().next().map(|c| c.to_ascii_uppercase()) {
+                Some('R') => match value.parse::<u8>() {
+                    Ok(r) if r >= 1 && r as usize <= MAX_RADIUS => radius = Some(r),
+                    _ => return Err(invalid()),
+                },
+                Some('C') => match value.parse::<u8>() {
+                    Ok(0) | Ok(1) => states = 2,
+                    Ok(n) => states = n,
+                    Err(_) => return Err(ParseRuleError::InvalidStates(value.to_string())),
+                },
+                Some('M') => match value {
+                    "0" => middle = false,
+                    "1" => middle = true,
+                    _ => return Err(invalid()),
+                },
+                Some('S') => survival = Some(bounds()?),
+                Some('B') => birth = Some(bounds()?),
+                Some('N') => match value {
+                    "M" | "m" => neighbourhood = Neighbourhood::Moore,
+                    "N" | "n" => neighbourhood = Neighbourhood::VonNeumann,
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(invalid()),
+            }
+        }
+        let (radius, survival, birth) = match (radius, survival, birth) {
+            (Some(radius), Some(survival), Some(birth)) => (radius, survival, birth),
+            _ => return Err(ParseRuleError::MissingSeparator),
+        };
+        if birth.0 == 0 {
+            return Err(ParseRuleError::BirthOnZero);
+        }
+        Ok(Rule {
+            birth: 0,
+            survival: 0,
+            states,
+            configurations: None,
+            neighbourhood,
+            range: Some(LargerThanLife {
+                radius,
+                middle,
+                survival,
+                birth,
+            }),
+            colors: 1,
+        })
+    }
+}
+
+// Larger rules reach further than a chunk's own halo can be gathered from.
+pub const MAX_RADIUS: usize = if SIZE < 10 { SIZE } else { 10 };
+
+// The cells counted as neighbours. The hexagonal neighbourhood is the Moore
+// one without the NE and SW corners, which is what a hexagonal grid looks
+// like when its rows are sheared into squares.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Neighbourhood {
+    #[default]
+    Moore,
+    VonNeumann,
+    Hexagonal,
+}
+impl Neighbourhood {
+    // The neighbours in a configuration (NW, N, NE, W, E, SW, S, SE from bit 0).
+    pub fn mask(self) -> u8 {
+        match self {
+            Neighbourhood::Moore => 0xff,
+            Neighbourhood::VonNeumann => 0b0101_1010,
+            Neighbourhood::Hexagonal => 0b1101_1011,
+        }
+    }
+    fn suffix(self) -> &'static str {
+        match self {
+            Neighbourhood::Moore => "",
+            Neighbourhood::VonNeumann => "V",
+            Neighbourhood::Hexagonal => "H",
+        }
+    }
+}
+
+// Larger than Life: a cell counts the live cells within `radius` (a square
+// for the Moore neighbourhood, a diamond for von Neumann), itself included
+// when `middle` is set, and survives/is born when the count is within the
+// inclusive `survival`/`birth` bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LargerThanLife {
+    pub radius: u8,
+    pub middle: bool,
+    pub survival: (u16, u16),
+    pub birth: (u16, u16),
+}
+impl LargerThanLife {
+    pub fn survives(&self, count: u16) -> bool {
+        self.survival.0 <= count && count <= self.survival.1
+    }
+    pub fn born(&self, count: u16) -> bool {
+        self.birth.0 <= count && count <= self.birth.1
+    }
+}
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::CONWAY
+    }
+}
+
+// Birth and survival per neighbourhood configuration: bit c of a set is for
+// the configuration whose bit i is set when neighbour i is alive, counting
+// NW, N, NE, W, E, SW, S, SE from bit 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Configurations {
+    pub birth: [u64; 4],
+    pub survival: [u64; 4],
+}
+
+fn contains(set: &[u64; 4], config: u8) -> bool {
+    set[config as usize / 64] & (1 << (config % 64)) != 0
+}
+
+// Hensel notation names each configuration of a neighbour count, up to
+// rotation and reflection, with a letter. The letters for each count in
+// their usual order, and one configuration for each letter of counts 1 to 4;
+// a count n > 4 configuration has the letter of its complement.
+const HENSEL: [&str; 9] = [
+    "",
+    "ce",
+    "ceaikn",
+    "ceaiknjqry",
+    "ceaiknjqrtwyz",
+    "ceaiknjqry",
+    "ceaikn",
+    "ce",
+    "",
+];
+const CANONICAL: [&[u8]; 5] = [
+    &[],
+    &[1, 2],
+    &[5, 10, 3, 24, 17, 36],
+    &[37, 26, 11, 7, 50, 13, 14, 38, 25, 49],
+    &[165, 90, 15, 29, 51, 39, 58, 54, 27, 53, 57, 46, 60],
+];
+
+// The eight rotations and reflections of a configuration.
+fn symmetries(config: u8) -> [u8; 8] {
+    const OFFSETS: [(i8, i8); 8] = [
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ];
+    let mut out = [0; 8];
+    for (i, image) in out.iter_mut().enumerate() {
+        for (bit, &(x, y)) in OFFSETS.iter().enumerate() {
+            if config & (1 << bit) == 0 {
+                continue;
+            }
+            let (mut x, mut y) = if i >= 4 { (-x, y) } else { (x, y) };
+            for _ in 0..i % 4 {
+                let turned = (-y, x);
+                x = turned.0;
+                y = turned.1;
+            }
+            let target = OFFSETS.iter().position(|&o| o == (x, y)).unwrap();
+            *image |= 1 << target;
+        }
+    }
+    out
+}
+
+// The Hensel letter of every configuration, or 0 for counts 0 and 8.
+pub fn hensel_letters() -> [u8; 256] {
+    let mut letters = [0; 256];
+    for n in 1..8 {
+        let base = n.min(8 - n);
+        for (letter, &config) in HENSEL[base].bytes().zip(CANONICAL[base].iter()) {
+            let config = if n > 4 { !config } else { config };
+            for image in symmetries(config).iter() {
+                letters[*image as usize] = letter;
+            }
+        }
+    }
+    letters
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseRuleError {
+    Empty,
+    MissingSeparator,
+    InvalidSection(String),
+    InvalidCount(char),
+    DuplicateCount(char),
+    InvalidLetter(char, u8),
+    BirthOnZero,
+    InvalidStates(String),
+    InvalidRange(String),
+    UnsupportedNeighbourhood,
+}
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseRuleError::Empty => write!(f, "rule string is empty"),
+            ParseRuleError::MissingSeparator => {
+                write!(f, "rule must have a birth and a survival part, e.g. B3/S23")
+            }
+            ParseRuleError::InvalidSection(s) => write!(f, "invalid rule section {:?}", s),
+            ParseRuleError::InvalidCount(c) => {
+                write!(f, "{:?} is not a neighbour count between 0 and 8", c)
+            }
+            ParseRuleError::DuplicateCount(c) => write!(f, "neighbour count {} repeated", c),
+            ParseRuleError::InvalidLetter(c, n) => {
+                write!(f, "{:?} is not a Hensel letter for {} neighbours", c, n)
+            }
+            ParseRuleError::BirthOnZero => write!(f, "B0 rules are not supported"),
+            ParseRuleError::InvalidStates(s) => {
+                write!(f, "{:?} is not a number of states between 2 and 255", s)
+            }
+            ParseRuleError::InvalidRange(s) => write!(
+                f,
+                "invalid Larger than Life field {:?} (radius up to {})",
+                s, MAX_RADIUS
+            ),
+            ParseRuleError::UnsupportedNeighbourhood => write!(
+                f,
+                "Hensel letters are only supported for the Moore neighbourhood"
+            ),
+        }
+    }
+}
+impl std::error::Error for ParseRuleError {}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+    // Accepts "B3/S23", "S23/B3", "B3S23" and the legacy "23/3" (survival/birth)
+    // form, plus a number of states for Generations rules: "B2/S/C3" or
+    // "/2/3" (survival/birth/states). Counts may be followed by lowercase
+    // Hensel letters, which pick out configurations ("B2a"), or a '-' and
+    // letters, which leave them out ("B2-a"). A trailing V or H picks the von
+    // Neumann or hexagonal neighbourhood, and "R2,C0,M0,S2..3,B3..3,NM" is
+    // a Larger than Life rule. "Immigration" and "QuadLife" name the
+    // coloured variants of Conway's rule.
+    fn from_str(s: &str) -> Result<Rule, ParseRuleError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseRuleError::Empty);
+        }
+        if s.eq_ignore_ascii_case("immigration") {
+            return Ok(Rule::IMMIGRATION);
+        }
+        if s.eq_ignore_ascii_case("quadlife") {
+            return Ok(Rule::QUADLIFE);
+        }
+        if s.starts_with(['R', 'r']) && s.contains(',') {
+            return Rule::parse_range(s);
+        }
+        let (s, neighbourhood) = match s.chars().last() {
+            Some('V') | Some('v') => (&s[..s.len() - 1], Neighbourhood::VonNeumann),
+            Some('H') | Some('h') => (&s[..s.len() - 1], Neighbourhood::Hexagonal),
+            _ => (s, Neighbourhood::Moore),
+        };
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
+        if s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // Sections start at a B or S, or at a C or G for the states; a
+            // lowercase c or g only starts one after a '/', since elsewhere it
+            // is a Hensel letter.
+            let mut sections: Vec<(char, String)> = Vec::new();
+            let mut after_slash = true;
+            for c in s.chars() {
+                if c == '/' {
+                    after_slash = true;
+                    continue;
+                }
+                let starts = matches!(c, 'B' | 'b' | 'S' | 's' | 'C' | 'G')
+                    || (after_slash && matches!(c, 'c' | 'g'));
+                after_slash = false;
+                if starts {
+                    sections.push((c.to_ascii_uppercase(), c.to_string()));
+                    continue;
+                }
+                match sections.last_mut() {
+                    Some(section) => section.1.push(c),
+                    None => return Err(ParseRuleError::InvalidSection(s.to_string())),
+                }
+            }
+            for (letter, section) in sections {
+                let body = &section[1..];
+                let duplicate = match letter {
+                    'B' => birth.replace(Rule::parse_counts(body)?).is_some(),
+                    'S' => survival.replace(Rule::parse_counts(body)?).is_some(),
+                    _ => states.replace(Rule::parse_states(body)?).is_some(),
+                };
+                if duplicate {
+                    return Err(ParseRuleError::InvalidSection(section));
+                }
+            }
+        } else {
+            let mut parts = s.split('/');
+            survival = Some(Rule::parse_counts(parts.next().unwrap_or(""))?);
+            birth = Some(Rule::parse_counts(
+                parts.next().ok_or(ParseRuleError::MissingSeparator)?,
+            )?);
+            if let Some(count) = parts.next() {
+                states = Some(Rule::parse_states(count)?);
+            }
+            if let Some(extra) = parts.next() {
+                return Err(ParseRuleError::InvalidSection(extra.to_string()));
+            }
+        }
+        let rule = match (birth, survival) {
+            (Some(birth), Some(survival)) => {
+                Rule::from_configurations(birth, survival, states.unwrap_or(2))
+            }
+            _ => return Err(ParseRuleError::MissingSeparator),
+        };
+        if neighbourhood != Neighbourhood::Moore {
+            if !rule.is_totalistic() {
+                return Err(ParseRuleError::UnsupportedNeighbourhood);
+            }
+            let max = neighbourhood.mask().count_ones();
+            if let Some(n) = (max + 1..9).find(|&n| (rule.birth | rule.survival) & (1 << n) != 0) {
+                return Err(ParseRuleError::InvalidCount(
+                    std::char::from_digit(n, 10).unwrap(),
+                ));
+            }
+        }
+        let rule = Rule {
+            neighbourhood,
+            ..rule
+        };
+        if rule.born_in(0) {
+            return Err(ParseRuleError::BirthOnZero);
+        }
+        Ok(rule)
+    }
+}
+
+// Writes each count with the Hensel letters of the configurations it
+// includes, or '-' and the ones it leaves out when that is shorter.
+fn write_counts<F: Fn(u8) -> bool>(f: &mut fmt::Formatter, includes: F) -> fmt::Result {
+    let letters = hensel_letters();
+    for n in 0..9 {
+        let mut present = String::new();
+        let mut missing = String::new();
+        for letter in HENSEL[n as usize].chars() {
+            let included = (0..=255u8)
+                .filter(|&c| c.count_ones() == n && letters[c as usize] as char == letter)
+                .all(&includes);
+            if included {
+                present.push(letter);
+            } else {
+                missing.push(letter);
+            }
+        }
+        if HENSEL[n as usize].is_empty() {
+            if (0..=255u8).any(|c| c.count_ones() == n && includes(c)) {
+                write!(f, "{}", n)?;
+            }
+        } else if missing.is_empty() {
+            write!(f, "{}", n)?;
+        } else if present.is_empty() {
+            continue;
+        } else if missing.len() < present.len() {
+            write!(f, "{}-{}", n, missing)?;
+        } else {
+            write!(f, "{}{}", n, present)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // colours only exist under the named rules
+        match self.colors {
+            2 => return write!(f, "Immigration"),
+            4 => return write!(f, "QuadLife"),
+            _ => {}
+        }
+        if let Some(range) = &self.range {
+            return write!(
+                f,
+                "R{},C{},M{},S{}..{},B{}..{},N{}",
+                range.radius,
+                if self.is_generations() {
+                    self.states
+                } else {
+                    0
+                },
+                range.middle as u8,
+                range.survival.0,
+                range.survival.1,
+                range.birth.0,
+                range.birth.1,
+                if self.neighbourhood == Neighbourhood::VonNeumann {
+                    'N'
+                } else {
+                    'M'
+                }
+            );
+        }
+        // counts are written as they are outside the Moore neighbourhood
+        let count = |config: u8| config.count_ones() as u8;
+        write!(f, "B")?;
+        if self.is_totalistic() {
+            write_counts(f, |config| self.born(count(config)))?;
+        } else {
+            write_counts(f, |config| self.born_in(config))?;
+        }
+        write!(f, "/S")?;
+        if self.is_totalistic() {
+            write_counts(f, |config| self.survives(count(config)))?;
+        } else {
+            write_counts(f, |config| self.survives_in(config))?;
+        }
+        if self.is_generations() {
+            write!(f, "/C{}", self.states)?;
+        }
+        write!(f, "{}", self.neighbourhood.suffix())
+    }
+}
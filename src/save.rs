@@ -0,0 +1,409 @@
+use std::fmt;
+use std::io;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::chunk::Chunk;
+use crate::chunk::Row;
+use crate::chunk::SIZE;
+use crate::rule::ParseRuleError;
+use crate::rule::Rule;
+use crate::table::RuleTable;
+use crate::table::TableError;
+use crate::topology::Topology;
+use crate::universe::Universe;
+
+// The .cgl save format. All integers are little-endian:
+//
+//     magic       4 bytes  "CGL\x1a"
+//     version     u16
+//     rule        u16 length + UTF-8 rule string
+//     table       u32 length + UTF-8 source of the rule table in use, or 0
+//     generation  u64
+//     bit_size    f32
+//     position    f32 x, f32 y
+//     chunk size  u16 side length N, one of 8, 16, 32 or 64
+//     topology    u8 kind (an index into `Topology::NAMES`), then i32 width and
+//                 i32 height in chunks (both 0 when unbounded)
+//     chunks      u32 count, then per chunk i32 x, i32 y and N rows of N / 8
+//                 little-endian bytes (x = 0 in the top bit), then a u8 count
+//                 of at most 8 state planes and that many more sets of N rows,
+//                 holding the state of dying cells and the colour of live ones
+//     checksum    u32 CRC-32 of everything before it
+//
+// Version 1 files have no chunk size field and always hold 8x8 chunks, and
+// versions 1 and 2 have no topology field and are always unbounded. Before
+// version 4 chunks have no state planes, and before version 5 there is no
+// rule table. Files without the magic number are read as the original
+// headerless dump of 16-byte native-endian 8x8 chunk records. Chunks of a
+// size other than the one this build uses are split or merged on load.
+
+pub const MAGIC: [u8; 4] = *b"CGL\x1a";
+pub const VERSION: u16 = 5;
+const LEGACY_SIZE: u16 = 8;
+const LEGACY_RECORD: usize = 16;
+// States are u8s, so eight planes hold any of them.
+const MAX_PLANES: u8 = 8;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Truncated(&'static str),
+    UnsupportedVersion(u16),
+    ChecksumMismatch { stored: u32, computed: u32 },
+    InvalidRule(ParseRuleError),
+    InvalidTable(TableError),
+    UnsupportedChunkSize(u16),
+    InvalidTopology(u8, i32, i32),
+    TooManyPlanes(u8),
+    TrailingBytes(usize),
+}
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{}", e),
+            SaveError::Truncated(field) => {
+                write!(f, "save file is truncated (ends inside the {})", field)
+            }
+            SaveError::UnsupportedVersion(v) => write!(
+                f,
+                "save file version {} is newer than the supported version {}",
+                v, VERSION
+            ),
+            SaveError::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "save file is corrupt (checksum {:08x}, expected {:08x})",
+                computed, stored
+            ),
+            SaveError::InvalidRule(e) => write!(f, "save file has an invalid rule: {}", e),
+            SaveError::InvalidTable(e) => write!(f, "save file has an invalid rule table: {}", e),
+            SaveError::UnsupportedChunkSize(n) => {
+                write!(f, "save file has {}x{} chunks, which can't be read", n, n)
+            }
+            SaveError::InvalidTopology(kind, width, height) => write!(
+                f,
+                "save file has an invalid topology (kind {}, {}x{})",
+                kind, width, height
+            ),
+            SaveError::TooManyPlanes(n) => write!(
+                f,
+                "save file has chunks with {} state planes (at most {} are supported)",
+                n, MAX_PLANES
+            ),
+            SaveError::TrailingBytes(n) => {
+                write!(f, "save file has {} unexpected bytes at the end", n)
+            }
+        }
+    }
+}
+impl std::error::Error for SaveError {}
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> SaveError {
+        SaveError::Io(e)
+    }
+}
+impl From<ParseRuleError> for SaveError {
+    fn from(e: ParseRuleError) -> SaveError {
+        SaveError::InvalidRule(e)
+    }
+}
+impl From<TableError> for SaveError {
+    fn from(e: TableError) -> SaveError {
+        SaveError::InvalidTable(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveData {
+    pub rule: Rule,
+    pub table: Option<Arc<RuleTable>>,
+    pub generation: u64,
+    pub bit_size: f32,
+    pub relative_pos: (f32, f32),
+    pub topology: Topology,
+    pub chunks: Vec<Chunk>,
+}
+impl Default for SaveData {
+    fn default() -> SaveData {
+        SaveData {
+            rule: Rule::default(),
+            table: None,
+            generation: 0,
+            bit_size: 10.0,
+            relative_pos: (0.0, 0.0),
+            topology: Topology::Unbounded,
+            chunks: Vec::new(),
+        }
+    }
+}
+
+pub fn write(data: &SaveData) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    let rule = data.rule.to_string();
+    bytes.extend_from_slice(&(rule.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(rule.as_bytes());
+    let table = data.table.as_ref().map_or("", |table| table.source());
+    bytes.extend_from_slice(&(table.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(table.as_bytes());
+    bytes.extend_from_slice(&data.generation.to_le_bytes());
+    bytes.extend_from_slice(&data.bit_size.to_le_bytes());
+    bytes.extend_from_slice(&data.relative_pos.0.to_le_bytes());
+    bytes.extend_from_slice(&data.relative_pos.1.to_le_bytes());
+    bytes.extend_from_slice(&(SIZE as u16).to_le_bytes());
+    let (width, height) = data.topology.size().unwrap_or((0, 0));
+    bytes.push(data.topology.kind());
+    bytes.extend_from_slice(&width.to_le_bytes());
+    bytes.extend_from_slice(&height.to_le_bytes());
+    bytes.extend_from_slice(&(data.chunks.len() as u32).to_le_bytes());
+    for chunk in data.chunks.iter() {
+        bytes.extend_from_slice(&chunk.pos[0].to_le_bytes());
+        bytes.extend_from_slice(&chunk.pos[1].to_le_bytes());
+        for row in chunk.chunk.iter() {
+            bytes.extend_from_slice(&row.to_le_bytes());
+        }
+        bytes.push(chunk.states.len() as u8);
+        for plane in chunk.states.iter() {
+            for row in plane.iter() {
+                bytes.extend_from_slice(&row.to_le_bytes());
+            }
+        }
+    }
+    let checksum = crc32(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+pub fn read(bytes: &[u8]) -> Result<SaveData, SaveError> {
+    if !bytes.starts_with(&MAGIC) {
+        return read_legacy(bytes);
+    }
+    if bytes.len() < MAGIC.len() + 6 {
+        return Err(SaveError::Truncated("header"));
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - 4);
+    let mut reader = Reader {
+        bytes: &body[MAGIC.len()..],
+    };
+    // Checked before any field, so that a damaged count or length is
+    // reported as corruption rather than as the file ending early.
+    let stored = u32::from_le_bytes(clone_into_array(checksum));
+    let computed = crc32(body);
+    if stored != computed {
+        return Err(SaveError::ChecksumMismatch { stored, computed });
+    }
+    let version = u16::from_le_bytes(reader.take("version")?);
+    if version > VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    let rule_len = u16::from_le_bytes(reader.take("rule length")?) as usize;
+    let rule = reader.take_slice(rule_len, "rule")?;
+    let rule = String::from_utf8_lossy(rule).parse()?;
+    let table = if version >= 5 {
+        let table_len = u32::from_le_bytes(reader.take("table length")?) as usize;
+        let table = reader.take_slice(table_len, "table")?;
+        if table.is_empty() {
+            None
+        } else {
+            Some(Arc::new(RuleTable::parse(&String::from_utf8_lossy(table))?))
+        }
+    } else {
+        None
+    };
+    let generation = u64::from_le_bytes(reader.take("generation")?);
+    let bit_size = f32::from_le_bytes(reader.take("zoom")?);
+    let relative_pos = (
+        f32::from_le_bytes(reader.take("position")?),
+        f32::from_le_bytes(reader.take("position")?),
+    );
+    let size = if version >= 2 {
+        u16::from_le_bytes(reader.take("chunk size")?)
+    } else {
+        LEGACY_SIZE
+    };
+    if ![8, 16, 32, 64].contains(&size) {
+        return Err(SaveError::UnsupportedChunkSize(size));
+    }
+    let topology = if version >= 3 {
+        let [kind] = reader.take("topology")?;
+        let width = i32::from_le_bytes(reader.take("topology")?);
+        let height = i32::from_le_bytes(reader.take("topology")?);
+        Topology::new(kind, width, height).ok_or(SaveError::InvalidTopology(kind, width, height))?
+    } else {
+        Topology::Unbounded
+    };
+    let count = u32::from_le_bytes(reader.take("chunk count")?);
+    let mut chunks = Chunks::new(size);
+    for _ in 0..count {
+        let pos = [
+            i32::from_le_bytes(reader.take("chunk records")?),
+            i32::from_le_bytes(reader.take("chunk records")?),
+        ];
+        let rows = reader.take_rows(size)?;
+        let mut planes = Vec::new();
+        if version >= 4 {
+            let [count] = reader.take("chunk records")?;
+            if count > MAX_PLANES {
+                return Err(SaveError::TooManyPlanes(count));
+            }
+            for _ in 0..count {
+                planes.push(reader.take_rows(size)?);
+            }
+        }
+        chunks.push(pos, rows, planes);
+    }
+    let chunks = chunks.finish();
+    if !reader.bytes.is_empty() {
+        return Err(SaveError::TrailingBytes(reader.bytes.len()));
+    }
+    Ok(SaveData {
+        rule,
+        table,
+        generation,
+        bit_size,
+        relative_pos,
+        topology,
+        chunks,
+    })
+}
+
+fn read_legacy(bytes: &[u8]) -> Result<SaveData, SaveError> {
+    if !bytes.chunks_exact(LEGACY_RECORD).remainder().is_empty() {
+        return Err(SaveError::Truncated("last chunk record"));
+    }
+    let mut chunks = Chunks::new(LEGACY_SIZE);
+    for record in bytes.chunks_exact(LEGACY_RECORD) {
+        let pos = [
+            i32::from_ne_bytes(clone_into_array(&record[0..4])),
+            i32::from_ne_bytes(clone_into_array(&record[4..8])),
+        ];
+        let rows = record[8..16].iter().map(|&row| row as u64).collect();
+        chunks.push(pos, rows, Vec::new());
+    }
+    Ok(SaveData {
+        chunks: chunks.finish(),
+        ..SaveData::default()
+    })
+}
+
+// Collects chunk records of the file's chunk size into chunks of this build's
+// size, going through absolute cell coordinates when the two differ.
+struct Chunks {
+    size: u16,
+    chunks: Vec<Chunk>,
+    regrouped: HashMap<[i32; 2], Chunk>,
+}
+impl Chunks {
+    fn new(size: u16) -> Chunks {
+        Chunks {
+            size,
+            chunks: Vec::new(),
+            regrouped: HashMap::new(),
+        }
+    }
+    // `rows` holds the live cells and `planes` the bits of higher states or
+    // colours.
+    fn push(&mut self, pos: [i32; 2], rows: Vec<u64>, planes: Vec<Vec<u64>>) {
+        if self.size as usize == SIZE {
+            let convert = |rows: &[u64]| {
+                let mut chunk = [0; SIZE];
+                for (dst, &row) in chunk.iter_mut().zip(rows) {
+                    *dst = row as Row;
+                }
+                chunk
+            };
+            let mut chunk = Chunk::from(pos, convert(&rows));
+            chunk.states = planes.iter().map(|plane| convert(plane)).collect();
+            self.chunks.push(chunk);
+            return;
+        }
+        let size = self.size as i32;
+        let bit = |row: u64, x: i32| row >> (size - 1 - x) & 1 != 0;
+        for y in 0..size as usize {
+            for x in 0..size {
+                let live = bit(rows[y], x);
+                let value = planes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, plane)| bit(plane[y], x))
+                    .fold(0, |state, (i, _)| state | 1 << i);
+                if live || value != 0 {
+                    let (pos, bit) =
+                        Universe::cell_pos(pos[0] * size + x, -pos[1] * size + y as i32);
+                    let chunk = self.regrouped.entry(pos).or_insert_with(|| Chunk::new(pos));
+                    if live {
+                        chunk.set_color(bit, value);
+                    } else {
+                        chunk.set_state(bit, value);
+                    }
+                }
+            }
+        }
+    }
+    fn finish(mut self) -> Vec<Chunk> {
+        let mut regrouped: Vec<Chunk> = self.regrouped.into_values().collect();
+        regrouped.sort_unstable_by_key(|chunk| chunk.pos);
+        self.chunks.append(&mut regrouped);
+        self.chunks
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> Reader<'a> {
+    fn take_slice(&mut self, len: usize, field: &'static str) -> Result<&'a [u8], SaveError> {
+        if self.bytes.len() < len {
+            return Err(SaveError::Truncated(field));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+    // One chunk's worth of rows of `size` bits.
+    fn take_rows(&mut self, size: u16) -> Result<Vec<u64>, SaveError> {
+        let bytes = self.take_slice(size as usize * size as usize / 8, "chunk records")?;
+        Ok(bytes
+            .chunks_exact(size as usize / 8)
+            .map(|row| {
+                let mut word = [0u8; 8];
+                word[..row.len()].copy_from_slice(row);
+                u64::from_le_bytes(word)
+            })
+            .collect())
+    }
+    fn take<A>(&mut self, field: &'static str) -> Result<A, SaveError>
+    where
+        A: Default + AsMut<[u8]>,
+    {
+        let mut a = A::default();
+        let len = a.as_mut().len();
+        a.as_mut().copy_from_slice(self.take_slice(len, field)?);
+        Ok(a)
+    }
+}
+
+// CRC-32 (IEEE 802.3), as used by zip and png.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffff_ffff;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn clone_into_array<A, T>(slice: &[T]) -> A
+where
+    A: Default + AsMut<[T]>,
+    T: Clone,
+{
+    let mut a = A::default();
+    <A as AsMut<[T]>>::as_mut(&mut a).clone_from_slice(slice);
+    a
+}
@@ -0,0 +1,29 @@
+// A rectangle of cells (x right, y down) including both corners, as picked
+// out by dragging with Shift held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+impl Selection {
+    // The rectangle with `a` and `b` at opposite corners, in either order.
+    pub fn new(a: (i32, i32), b: (i32, i32)) -> Selection {
+        Selection {
+            left: a.0.min(b.0),
+            top: a.1.min(b.1),
+            right: a.0.max(b.0),
+            bottom: a.1.max(b.1),
+        }
+    }
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.left..=self.right).contains(&x) && (self.top..=self.bottom).contains(&y)
+    }
+    pub fn width(&self) -> i32 {
+        self.right - self.left + 1
+    }
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top + 1
+    }
+}
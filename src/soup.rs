@@ -0,0 +1,199 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::pattern::{Pattern, Transform};
+
+// SplitMix64, a small generator whose output depends only on the seed, so a
+// soup comes out the same on every machine.
+pub struct Random(u64);
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random(seed)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    // Uniform in [0, 1), from the top 53 bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The symmetries soups can have, named as in apgsearch. The digit after C2,
+// C4 and the D4/D8 groups says where the centre lies: 1 on a cell, 2 on the
+// middle of an edge and 4 on a corner. D2_+ mirrors left to right through a
+// column of cells (1) or between two columns (2), and D2_x in a diagonal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    #[default]
+    C1,
+    C2_1,
+    C2_2,
+    C2_4,
+    C4_1,
+    C4_4,
+    D2Plus1,
+    D2Plus2,
+    D2X,
+    D4Plus1,
+    D4Plus2,
+    D4Plus4,
+    D4X1,
+    D4X4,
+    D8_1,
+    D8_4,
+}
+impl Symmetry {
+    pub const ALL: [Symmetry; 16] = [
+        Symmetry::C1,
+        Symmetry::C2_1,
+        Symmetry::C2_2,
+        Symmetry::C2_4,
+        Symmetry::C4_1,
+        Symmetry::C4_4,
+        Symmetry::D2Plus1,
+        Symmetry::D2Plus2,
+        Symmetry::D2X,
+        Symmetry::D4Plus1,
+        Symmetry::D4Plus2,
+        Symmetry::D4Plus4,
+        Symmetry::D4X1,
+        Symmetry::D4X4,
+        Symmetry::D8_1,
+        Symmetry::D8_4,
+    ];
+    const NAMES: [&'static str; 16] = [
+        "C1", "C2_1", "C2_2", "C2_4", "C4_1", "C4_4", "D2_+1", "D2_+2", "D2_x", "D4_+1", "D4_+2",
+        "D4_+4", "D4_x1", "D4_x4", "D8_1", "D8_4",
+    ];
+    // Transforms of the soup's box that generate the symmetry group.
+    pub fn transforms(self) -> &'static [Transform] {
+        match self {
+            Symmetry::C1 => &[],
+            Symmetry::C2_1 | Symmetry::C2_2 | Symmetry::C2_4 => &[Transform::Rotate180],
+            Symmetry::C4_1 | Symmetry::C4_4 => &[Transform::Rotate90],
+            Symmetry::D2Plus1 | Symmetry::D2Plus2 => &[Transform::FlipHorizontal],
+            Symmetry::D2X => &[Transform::FlipDiagonal],
+            Symmetry::D4Plus1 | Symmetry::D4Plus2 | Symmetry::D4Plus4 => {
+                &[Transform::FlipHorizontal, Transform::FlipVertical]
+            }
+            Symmetry::D4X1 | Symmetry::D4X4 => {
+                &[Transform::FlipDiagonal, Transform::FlipAntidiagonal]
+            }
+            Symmetry::D8_1 | Symmetry::D8_4 => &[Transform::Rotate90, Transform::FlipHorizontal],
+        }
+    }
+    // Whether the box has to be square, and whether its width and height
+    // have to be odd (true) or even (false) to put the centre in the right
+    // place.
+    fn shape(self) -> (bool, Option<bool>, Option<bool>) {
+        match self {
+            Symmetry::C1 => (false, None, None),
+            Symmetry::C2_1 | Symmetry::D4Plus1 => (false, Some(true), Some(true)),
+            Symmetry::C2_2 | Symmetry::D4Plus2 => (false, Some(true), Some(false)),
+            Symmetry::C2_4 | Symmetry::D4Plus4 => (false, Some(false), Some(false)),
+            Symmetry::D2Plus1 => (false, Some(true), None),
+            Symmetry::D2Plus2 => (false, Some(false), None),
+            Symmetry::D2X => (true, None, None),
+            Symmetry::C4_1 | Symmetry::D4X1 | Symmetry::D8_1 => (true, Some(true), Some(true)),
+            Symmetry::C4_4 | Symmetry::D4X4 | Symmetry::D8_4 => (true, Some(false), Some(false)),
+        }
+    }
+    // The largest box no bigger than width by height that this symmetry
+    // fits, except that a side of one cell grows to two if it must be even.
+    pub fn fit(self, width: i32, height: i32) -> (i32, i32) {
+        let (square, odd_width, odd_height) = self.shape();
+        let (width, height) = if square {
+            (width.min(height), width.min(height))
+        } else {
+            (width, height)
+        };
+        let fit = |side: i32, odd: Option<bool>| match odd {
+            Some(odd) if (side % 2 != 0) != odd => {
+                if side > 1 {
+                    side - 1
+                } else {
+                    side + 1
+                }
+            }
+            _ => side.max(1),
+        };
+        (fit(width, odd_width), fit(height, odd_height))
+    }
+}
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let index = Symmetry::ALL.iter().position(|s| s == self).unwrap_or(0);
+        write!(f, "{}", Symmetry::NAMES[index])
+    }
+}
+impl FromStr for Symmetry {
+    type Err = ParseSymmetryError;
+    fn from_str(s: &str) -> Result<Symmetry, ParseSymmetryError> {
+        let s = s.trim();
+        Symmetry::NAMES
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(s))
+            .map(|i| Symmetry::ALL[i])
+            .ok_or_else(|| ParseSymmetryError::UnknownName(s.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseSymmetryError {
+    UnknownName(String),
+}
+impl fmt::Display for ParseSymmetryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseSymmetryError::UnknownName(name) => write!(
+                f,
+                "unknown symmetry {:?} (expected one of {})",
+                name,
+                Symmetry::NAMES.join(", ")
+            ),
+        }
+    }
+}
+impl std::error::Error for ParseSymmetryError {}
+
+// A random soup filling a width by height box from (0, 0), shrunk to fit
+// the symmetry (see `Symmetry::fit`). Each cell is live with probability
+// `density`, and cells that the symmetry maps onto each other match.
+pub fn soup(width: i32, height: i32, density: f64, symmetry: Symmetry, seed: u64) -> Pattern {
+    let (width, height) = symmetry.fit(width, height);
+    let mut random = Random::new(seed);
+    let mut cells = Vec::new();
+    let mut done = vec![false; (width * height) as usize];
+    let index = |(x, y): (i32, i32)| (y * width + x) as usize;
+    for y in 0..height {
+        for x in 0..width {
+            if done[index((x, y))] {
+                continue;
+            }
+            let live = random.next_f64() < density;
+            // every image of the cell under the group
+            let mut orbit = vec![(x, y)];
+            done[index((x, y))] = true;
+            let mut i = 0;
+            while i < orbit.len() {
+                for transform in symmetry.transforms() {
+                    let image = transform.apply(orbit[i], (width, height));
+                    if !done[index(image)] {
+                        done[index(image)] = true;
+                        orbit.push(image);
+                    }
+                }
+                i += 1;
+            }
+            if live {
+                cells.extend(orbit);
+            }
+        }
+    }
+    Pattern::from_cells(cells)
+}
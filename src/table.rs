@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::rule::Neighbourhood;
+use crate::rule::Rule;
+
+// Rule tables in Golly's .rule format:
+//
+//     @RULE WireWorld
+//     @TABLE
+//     n_states:4
+//     neighborhood:Moore
+//     symmetries:permute
+//     var a={0,1,2,3}
+//     1,a,b,c,d,e,f,g,h,2
+//     @COLORS
+//     1 0 128 255
+//
+// Each transition lists the cell's state, its neighbours clockwise from N and
+// the state it changes to. A variable stands for any of its states, and one
+// used more than once in a transition has the same state each time. The
+// first transition that matches a cell wins, and a cell no transition matches
+// keeps its state. Other sections such as @ICONS are skipped.
+
+const WIREWORLD: &str = "@RULE WireWorld
+Electron heads (1) move along wires (3), leaving tails (2) behind them.
+
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+var i={0,2,3}
+var j={i}
+var k={i}
+var l={i}
+var m={i}
+var n={i}
+var o={i}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1
+3,1,1,j,k,l,m,n,o,1
+
+@COLORS
+0 48 48 48
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+// Transitions a table may expand into before it is refused.
+const MAX_EXPANSION: usize = 1 << 22;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableError {
+    Missing(&'static str),
+    InvalidStates(String),
+    UnsupportedNeighbourhood(String),
+    UnsupportedSymmetry(String),
+    InvalidVariable(usize, String),
+    InvalidTransition(usize, String),
+    InvalidColor(usize, String),
+    TooLarge(usize),
+}
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableError::Missing(what) => write!(f, "rule table has no {}", what),
+            TableError::InvalidStates(s) => write!(f, "invalid number of states {:?}", s),
+            TableError::UnsupportedNeighbourhood(s) => {
+                write!(f, "unsupported rule table neighborhood {:?}", s)
+            }
+            TableError::UnsupportedSymmetry(s) => {
+                write!(f, "unsupported rule table symmetry {:?}", s)
+            }
+            TableError::InvalidVariable(line, s) => {
+                write!(f, "invalid variable on line {}: {:?}", line, s)
+            }
+            TableError::InvalidTransition(line, s) => {
+                write!(f, "invalid transition on line {}: {:?}", line, s)
+            }
+            TableError::InvalidColor(line, s) => {
+                write!(f, "invalid colour on line {}: {:?}", line, s)
+            }
+            TableError::TooLarge(line) => write!(
+                f,
+                "rule table expands into too many transitions at line {}",
+                line
+            ),
+        }
+    }
+}
+impl std::error::Error for TableError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Symmetry {
+    // Rotations by each multiple of 1/n of a turn, plus their mirror images
+    // when reflecting.
+    Rotate(usize, bool),
+    // Any order of the neighbours.
+    Permute,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleTable {
+    pub name: String,
+    pub states: u8,
+    pub neighbourhood: Neighbourhood,
+    // Colours from @COLORS, by state.
+    pub colors: Vec<(u8, [u8; 3])>,
+    // The next state of each neighbourhood a transition names, keyed by the
+    // cell's state followed by its neighbours clockwise from N (sorted when
+    // their order doesn't matter), with unused slots 0.
+    transitions: HashMap<[u8; 9], u8>,
+    permute: bool,
+    // The text the table was read from, kept for saving.
+    source: String,
+}
+impl RuleTable {
+    pub fn parse(text: &str) -> Result<RuleTable, TableError> {
+        let mut name = None;
+        let mut section = "";
+        let mut has_table = false;
+        let mut states = None;
+        let mut neighbourhood = Neighbourhood::Moore;
+        let mut symmetry = Symmetry::Rotate(1, false);
+        let mut variables: HashMap<&str, Vec<u8>> = HashMap::new();
+        let mut transitions = HashMap::new();
+        let mut expanded = 0;
+        let mut colors = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let n = n + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if let Some(header) = line.strip_prefix('@') {
+                let mut words = header.split_whitespace();
+                section = words.next().unwrap_or("");
+                if section == "RULE" {
+                    name = words.next().map(str::to_string);
+                }
+                has_table |= section == "TABLE";
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if section == "COLORS" {
+                colors.extend(parse_colors(n, line, states)?);
+                continue;
+            }
+            if section != "TABLE" {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                match key.trim() {
+                    "n_states" => match value.parse::<u8>() {
+                        Ok(n) if n >= 2 => states = Some(n),
+                        _ => return Err(TableError::InvalidStates(value.to_string())),
+                    },
+                    "neighborhood" => {
+                        neighbourhood = match value {
+                            "Moore" => Neighbourhood::Moore,
+                            "vonNeumann" => Neighbourhood::VonNeumann,
+                            "hexagonal" => Neighbourhood::Hexagonal,
+                            _ => {
+                                return Err(TableError::UnsupportedNeighbourhood(value.to_string()))
+                            }
+                        }
+                    }
+                    "symmetries" => symmetry = parse_symmetry(value)?,
+                    _ => return Err(TableError::InvalidTransition(n, line.to_string())),
+                }
+                continue;
+            }
+            let states = states.ok_or(TableError::Missing("n_states"))?;
+            if let Some(var) = line.strip_prefix("var ") {
+                let (var, values) = parse_variable(n, var, states, &variables)?;
+                variables.insert(var, values);
+                continue;
+            }
+            let variants = variants(offsets(neighbourhood).len(), symmetry)?;
+            let permute = symmetry == Symmetry::Permute;
+            expanded += expand(
+                n,
+                line,
+                states,
+                &variables,
+                &variants,
+                permute,
+                &mut transitions,
+            )?;
+            if expanded > MAX_EXPANSION {
+                return Err(TableError::TooLarge(n));
+            }
+        }
+        let name = name.ok_or(TableError::Missing("@RULE name"))?;
+        if !has_table {
+            return Err(TableError::Missing("@TABLE section"));
+        }
+        let states = states.ok_or(TableError::Missing("n_states"))?;
+        // checked here too for tables without transitions
+        variants(offsets(neighbourhood).len(), symmetry)?;
+        Ok(RuleTable {
+            name,
+            states,
+            neighbourhood,
+            colors,
+            transitions,
+            permute: symmetry == Symmetry::Permute,
+            source: text.to_string(),
+        })
+    }
+    pub fn wireworld() -> RuleTable {
+        RuleTable::parse(WIREWORLD).expect("built-in rule table is valid")
+    }
+    // The tables that come with the program, by name.
+    pub fn builtin(name: &str) -> Option<RuleTable> {
+        if name.eq_ignore_ascii_case("WireWorld") {
+            Some(RuleTable::wireworld())
+        } else {
+            None
+        }
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    // (dx, dy) of each neighbour, clockwise from N.
+    pub fn offsets(&self) -> &'static [(i32, i32)] {
+        offsets(self.neighbourhood)
+    }
+    // The next state of the cell `cells[0]` whose neighbours, clockwise from
+    // N, are `cells[1..]`.
+    pub fn next(&self, cells: [u8; 9]) -> u8 {
+        let mut key = cells;
+        if self.permute {
+            key[1..=self.offsets().len()].sort_unstable();
+        }
+        self.transitions.get(&key).copied().unwrap_or(cells[0])
+    }
+    pub fn color(&self, state: u8) -> Option<[u8; 3]> {
+        self.colors
+            .iter()
+            .rev()
+            .find(|&&(s, _)| s == state)
+            .map(|&(_, color)| color)
+    }
+    // The outer-totalistic rule a two-state table amounts to, if it is one,
+    // so that it can be run by the bit-parallel stepper instead.
+    pub fn to_rule(&self) -> Option<Rule> {
+        if self.states != 2 {
+            return None;
+        }
+        let count = self.offsets().len();
+        let mut next: [[Option<bool>; 9]; 2] = [[None; 9]; 2];
+        for (state, next) in next.iter_mut().enumerate() {
+            for config in 0..1u32 << count {
+                let mut cells = [0; 9];
+                cells[0] = state as u8;
+                for (i, cell) in cells[1..=count].iter_mut().enumerate() {
+                    *cell = (config >> i & 1) as u8;
+                }
+                let live = self.next(cells) == 1;
+                match &mut next[config.count_ones() as usize] {
+                    Some(seen) if *seen != live => return None,
+                    slot => *slot = Some(live),
+                }
+            }
+        }
+        let counts = |state: usize| -> Vec<u8> {
+            (0..=count as u8)
+                .filter(|&n| next[state][n as usize] == Some(true))
+                .collect()
+        };
+        let birth = counts(0);
+        if birth.contains(&0) {
+            return None;
+        }
+        Some(Rule {
+            neighbourhood: self.neighbourhood,
+            ..Rule::new(&birth, &counts(1))
+        })
+    }
+}
+
+fn offsets(neighbourhood: Neighbourhood) -> &'static [(i32, i32)] {
+    match neighbourhood {
+        Neighbourhood::Moore => &[
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+        ],
+        Neighbourhood::VonNeumann => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+        Neighbourhood::Hexagonal => &[(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)],
+    }
+}
+
+fn parse_symmetry(value: &str) -> Result<Symmetry, TableError> {
+    let symmetry = match value {
+        "none" => Symmetry::Rotate(1, false),
+        "reflect_horizontal" => Symmetry::Rotate(1, true),
+        "permute" => Symmetry::Permute,
+        _ => {
+            let rest = value.strip_prefix("rotate");
+            let (turns, reflect) = match rest.and_then(|rest| rest.strip_suffix("reflect")) {
+                Some(turns) => (turns, true),
+                None => (rest.unwrap_or(""), false),
+            };
+            match turns.parse::<usize>() {
+                Ok(turns) if [2, 3, 4, 6, 8].contains(&turns) => Symmetry::Rotate(turns, reflect),
+                _ => return Err(TableError::UnsupportedSymmetry(value.to_string())),
+            }
+        }
+    };
+    Ok(symmetry)
+}
+
+// The orders a transition's neighbours are also tried in, as the index of
+// the neighbour that lands in each slot. Neighbours run clockwise, so a
+// rotation shifts them round and a reflection reverses them.
+fn variants(neighbours: usize, symmetry: Symmetry) -> Result<Vec<Vec<usize>>, TableError> {
+    let (turns, reflect) = match symmetry {
+        Symmetry::Rotate(turns, reflect) => (turns, reflect),
+        Symmetry::Permute => return Ok(vec![(0..neighbours).collect()]),
+    };
+    if neighbours % turns != 0 {
+        return Err(TableError::UnsupportedSymmetry(format!(
+            "rotate{} with {} neighbours",
+            turns, neighbours
+        )));
+    }
+    let mut variants = Vec::new();
+    for turn in 0..turns {
+        let shift = turn * neighbours / turns;
+        variants.push((0..neighbours).map(|i| (i + shift) % neighbours).collect());
+        if reflect {
+            variants.push(
+                (0..neighbours)
+                    .map(|i| (neighbours - i + shift) % neighbours)
+                    .collect(),
+            );
+        }
+    }
+    Ok(variants)
+}
+
+// "a={0,1,b}": a name and its states, which may include other variables'.
+fn parse_variable<'a>(
+    n: usize,
+    text: &'a str,
+    states: u8,
+    variables: &HashMap<&str, Vec<u8>>,
+) -> Result<(&'a str, Vec<u8>), TableError> {
+    let invalid = || TableError::InvalidVariable(n, text.to_string());
+    let (name, values) = text.split_once('=').ok_or_else(invalid)?;
+    let name = name.trim();
+    let values = values
+        .trim()
+        .strip_prefix('{')
+        .and_then(|values| values.strip_suffix('}'))
+        .ok_or_else(invalid)?;
+    if name.is_empty() || name.parse::<u8>().is_ok() || name.contains([',', '{', '}']) {
+        return Err(invalid());
+    }
+    let mut set = Vec::new();
+    for value in values.split(',').map(str::trim) {
+        match value.parse::<u8>() {
+            Ok(state) if state < states => set.push(state),
+            Ok(_) => return Err(invalid()),
+            Err(_) => set.extend(variables.get(value).ok_or_else(invalid)?),
+        }
+    }
+    set.sort_unstable();
+    set.dedup();
+    if set.is_empty() {
+        return Err(invalid());
+    }
+    Ok((name, set))
+}
+
+// Adds every neighbourhood a transition line covers, returning how many
+// there were.
+fn expand(
+    n: usize,
+    line: &str,
+    states: u8,
+    variables: &HashMap<&str, Vec<u8>>,
+    variants: &[Vec<usize>],
+    permute: bool,
+    transitions: &mut HashMap<[u8; 9], u8>,
+) -> Result<usize, TableError> {
+    let invalid = || TableError::InvalidTransition(n, line.to_string());
+    // commas can be left out when every state and variable is one character
+    let items: Vec<String> = if line.contains(',') {
+        line.split(',')
+            .map(|item| item.trim().to_string())
+            .collect()
+    } else {
+        line.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(String::from)
+            .collect()
+    };
+    let neighbours = variants[0].len();
+    if items.len() != neighbours + 2 {
+        return Err(invalid());
+    }
+    // each item is a state, or the index of a variable in `bound`
+    let mut bound: Vec<(&str, &Vec<u8>)> = Vec::new();
+    let mut slots: Vec<Result<u8, usize>> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        if let Ok(state) = item.parse::<u8>() {
+            if state >= states {
+                return Err(invalid());
+            }
+            slots.push(Ok(state));
+            continue;
+        }
+        let index = match bound.iter().position(|&(name, _)| name == item) {
+            Some(index) => index,
+            // the new state must come from a variable that is already bound
+            None if i == neighbours + 1 => return Err(invalid()),
+            None => {
+                let values = variables.get(item.as_str()).ok_or_else(invalid)?;
+                bound.push((item, values));
+                bound.len() - 1
+            }
+        };
+        slots.push(Err(index));
+    }
+    let total = bound
+        .iter()
+        .try_fold(1usize, |total, (_, values)| total.checked_mul(values.len()))
+        .filter(|&total| total <= MAX_EXPANSION)
+        .ok_or(TableError::TooLarge(n))?;
+    let mut choice = vec![0; bound.len()];
+    for _ in 0..total {
+        let state = |slot: &Result<u8, usize>| match *slot {
+            Ok(state) => state,
+            Err(index) => bound[index].1[choice[index]],
+        };
+        let mut cells = [0; 9];
+        for (cell, slot) in cells.iter_mut().zip(slots[..=neighbours].iter()) {
+            *cell = state(slot);
+        }
+        let next = state(&slots[neighbours + 1]);
+        for variant in variants {
+            let mut key = [0; 9];
+            key[0] = cells[0];
+            for (slot, &from) in key[1..].iter_mut().zip(variant.iter()) {
+                *slot = cells[1 + from];
+            }
+            if permute {
+                key[1..=neighbours].sort_unstable();
+            }
+            transitions.entry(key).or_insert(next);
+        }
+        // next combination of the variables' states
+        for (index, (_, values)) in bound.iter().enumerate() {
+            choice[index] += 1;
+            if choice[index] < values.len() {
+                break;
+            }
+            choice[index] = 0;
+        }
+    }
+    Ok(total)
+}
+
+// "1 255 0 0" colours a state; "255 0 0 0 0 255" runs a gradient from the
+// first to the last live state.
+fn parse_colors(
+    n: usize,
+    line: &str,
+    states: Option<u8>,
+) -> Result<Vec<(u8, [u8; 3])>, TableError> {
+    let invalid = || TableError::InvalidColor(n, line.to_string());
+    let numbers = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse::<u8>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u8>, TableError>>()?;
+    match numbers[..] {
+        [state, r, g, b] => Ok(vec![(state, [r, g, b])]),
+        [r1, g1, b1, r2, g2, b2] => {
+            let states = states.ok_or_else(invalid)?;
+            let last = (states - 1).max(2) - 1;
+            let mix = |a: u8, b: u8, t: u8| {
+                (a as u32 * (last - t) as u32 / last as u32 + b as u32 * t as u32 / last as u32)
+                    as u8
+            };
+            Ok((1..states)
+                .map(|state| {
+                    let t = state - 1;
+                    (state, [mix(r1, r2, t), mix(g1, g2, t), mix(b1, b2, t)])
+                })
+                .collect())
+        }
+        _ => Err(invalid()),
+    }
+}
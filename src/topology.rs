@@ -0,0 +1,256 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chunk::SIZE;
+
+// The shape of the universe. Bounded worlds are measured in chunks and cover
+// the cells from (0, 0) to (width * SIZE - 1, height * SIZE - 1). Each pair of
+// opposite edges is either a wall with permanently dead cells beyond it,
+// joined so that leaving one edge re-enters at the other, or joined with a
+// twist that also mirrors the position along the edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    // The infinite plane.
+    #[default]
+    Unbounded,
+    // Walls on all four sides.
+    Plane {
+        width: i32,
+        height: i32,
+    },
+    // Both pairs of edges joined.
+    Torus {
+        width: i32,
+        height: i32,
+    },
+    // Left and right edges joined, walls at the top and bottom.
+    HorizontalCylinder {
+        width: i32,
+        height: i32,
+    },
+    // Top and bottom edges joined, walls at the left and right.
+    VerticalCylinder {
+        width: i32,
+        height: i32,
+    },
+    // Left and right edges joined, top and bottom joined with a twist.
+    KleinBottle {
+        width: i32,
+        height: i32,
+    },
+    // Both pairs of edges joined with a twist.
+    CrossSurface {
+        width: i32,
+        height: i32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Join {
+    Wall,
+    Wrap,
+    Twist,
+}
+
+impl Topology {
+    pub const NAMES: [&'static str; 7] = [
+        "unbounded",
+        "plane",
+        "torus",
+        "hcylinder",
+        "vcylinder",
+        "klein",
+        "cross",
+    ];
+    // None for an unknown kind, or a size that is empty or whose cells
+    // wouldn't all have i32 coordinates.
+    pub fn new(kind: u8, width: i32, height: i32) -> Option<Topology> {
+        if kind != 0 && (width <= 0 || height <= 0 || Topology::too_large(width, height)) {
+            return None;
+        }
+        Some(match kind {
+            0 => Topology::Unbounded,
+            1 => Topology::Plane { width, height },
+            2 => Topology::Torus { width, height },
+            3 => Topology::HorizontalCylinder { width, height },
+            4 => Topology::VerticalCylinder { width, height },
+            5 => Topology::KleinBottle { width, height },
+            6 => Topology::CrossSurface { width, height },
+            _ => return None,
+        })
+    }
+    fn too_large(width: i32, height: i32) -> bool {
+        width.checked_mul(SIZE as i32).is_none() || height.checked_mul(SIZE as i32).is_none()
+    }
+    // Index into `NAMES`, as stored in save files.
+    pub fn kind(&self) -> u8 {
+        match self {
+            Topology::Unbounded => 0,
+            Topology::Plane { .. } => 1,
+            Topology::Torus { .. } => 2,
+            Topology::HorizontalCylinder { .. } => 3,
+            Topology::VerticalCylinder { .. } => 4,
+            Topology::KleinBottle { .. } => 5,
+            Topology::CrossSurface { .. } => 6,
+        }
+    }
+    // Width and height in chunks, for bounded worlds.
+    pub fn size(&self) -> Option<(i32, i32)> {
+        match *self {
+            Topology::Unbounded => None,
+            Topology::Plane { width, height }
+            | Topology::Torus { width, height }
+            | Topology::HorizontalCylinder { width, height }
+            | Topology::VerticalCylinder { width, height }
+            | Topology::KleinBottle { width, height }
+            | Topology::CrossSurface { width, height } => Some((width, height)),
+        }
+    }
+    // How the left/right and top/bottom edges are joined.
+    fn joins(&self) -> (Join, Join) {
+        match self {
+            Topology::Unbounded | Topology::Torus { .. } => (Join::Wrap, Join::Wrap),
+            Topology::Plane { .. } => (Join::Wall, Join::Wall),
+            Topology::HorizontalCylinder { .. } => (Join::Wrap, Join::Wall),
+            Topology::VerticalCylinder { .. } => (Join::Wall, Join::Wrap),
+            Topology::KleinBottle { .. } => (Join::Wrap, Join::Twist),
+            Topology::CrossSurface { .. } => (Join::Twist, Join::Twist),
+        }
+    }
+    pub fn is_bounded(&self) -> bool {
+        *self != Topology::Unbounded
+    }
+    // Cell bounds as (min_x, min_y, max_x, max_y), for bounded worlds.
+    pub fn bounds(&self) -> Option<(i32, i32, i32, i32)> {
+        let (width, height) = self.size()?;
+        Some((0, 0, width * SIZE as i32 - 1, height * SIZE as i32 - 1))
+    }
+    // Where (x, y) lands on a grid of the given size, with y pointing down.
+    fn wrap(&self, x: i32, y: i32, width: i32, height: i32) -> Option<(i32, i32)> {
+        let (horizontal, vertical) = self.joins();
+        let (mut x, mut y) = (x, y);
+        if x < 0 || x >= width {
+            match horizontal {
+                Join::Wall => return None,
+                Join::Wrap => {}
+                Join::Twist if x.div_euclid(width) % 2 != 0 => y = height - 1 - y,
+                Join::Twist => {}
+            }
+            x = x.rem_euclid(width);
+        }
+        if y < 0 || y >= height {
+            match vertical {
+                Join::Wall => return None,
+                Join::Wrap => {}
+                Join::Twist if y.div_euclid(height) % 2 != 0 => x = width - 1 - x,
+                Join::Twist => {}
+            }
+            y = y.rem_euclid(height);
+        }
+        Some((x, y))
+    }
+    // Where the chunk at `pos` really is, or `None` if it is outside the world.
+    // Chunk keys have y pointing up, so rows of chunks run from 0 down to
+    // 1 - height.
+    pub fn wrap_chunk(&self, pos: [i32; 2]) -> Option<[i32; 2]> {
+        let (width, height) = match self.size() {
+            Some(size) => size,
+            None => return Some(pos),
+        };
+        let (x, y) = self.wrap(pos[0], -pos[1], width, height)?;
+        Some([x, -y])
+    }
+    // Where the cell at (x, y) really is, or `None` if it is outside the world.
+    pub fn wrap_cell(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (width, height) = match self.size() {
+            Some(size) => size,
+            None => return Some((x, y)),
+        };
+        self.wrap(x, y, width * SIZE as i32, height * SIZE as i32)
+    }
+    // Whether the chunk at `pos` touches a twisted edge. Its neighbours there
+    // are mirrored, so its halo has to be gathered cell by cell.
+    pub fn twists(&self, pos: [i32; 2]) -> bool {
+        let (width, height) = match self.size() {
+            Some(size) => size,
+            None => return false,
+        };
+        let (horizontal, vertical) = self.joins();
+        (horizontal == Join::Twist && (pos[0] == 0 || pos[0] == width - 1))
+            || (vertical == Join::Twist && (pos[1] == 0 || pos[1] == 1 - height))
+    }
+}
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = Topology::NAMES[self.kind() as usize];
+        match self.size() {
+            Some((width, height)) => write!(f, "{}:{}x{}", name, width, height),
+            None => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseTopologyError {
+    UnknownName(String),
+    InvalidSize(String),
+    TooLarge(i32, i32),
+}
+impl fmt::Display for ParseTopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseTopologyError::UnknownName(name) => write!(
+                f,
+                "unknown topology '{}' (expected one of {})",
+                name,
+                Topology::NAMES.join(", ")
+            ),
+            ParseTopologyError::InvalidSize(size) => write!(
+                f,
+                "invalid world size '{}' (expected width x height in chunks, like 4x3)",
+                size
+            ),
+            ParseTopologyError::TooLarge(width, height) => write!(
+                f,
+                "world size {}x{} is too large (at most {} chunks a side)",
+                width,
+                height,
+                i32::MAX / SIZE as i32
+            ),
+        }
+    }
+}
+impl std::error::Error for ParseTopologyError {}
+
+// Parses the `Display` form: "unbounded", or a name and a size in chunks such
+// as "klein:4x3".
+impl FromStr for Topology {
+    type Err = ParseTopologyError;
+    fn from_str(s: &str) -> Result<Topology, ParseTopologyError> {
+        let s = s.trim();
+        let (name, size) = match s.split_once(':') {
+            Some((name, size)) => (name, Some(size)),
+            None => (s, None),
+        };
+        let kind = Topology::NAMES
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .ok_or_else(|| ParseTopologyError::UnknownName(name.to_string()))?;
+        let invalid = || ParseTopologyError::InvalidSize(size.unwrap_or("").to_string());
+        let (width, height) = match size {
+            None if kind == 0 => (0, 0),
+            Some(size) if kind != 0 => {
+                let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+                (
+                    width.trim().parse().map_err(|_| invalid())?,
+                    height.trim().parse().map_err(|_| invalid())?,
+                )
+            }
+            _ => return Err(invalid()),
+        };
+        if Topology::too_large(width, height) {
+            return Err(ParseTopologyError::TooLarge(width, height));
+        }
+        Topology::new(kind as u8, width, height).ok_or_else(invalid)
+    }
+}
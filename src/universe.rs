@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::chunk::Chunk;
+use crate::chunk::Edges;
+use crate::chunk::Halo;
+use crate::chunk::Row;
+use crate::chunk::SIZE;
+use crate::engine::Engine;
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use crate::table::RuleTable;
+use crate::topology::Topology;
+
+// Source of `Universe::revision`s, shared by every universe so that no two
+// different sets of cells ever have the same one.
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+// Cell data and evolution, independent of any renderer. Absolute cell
+// coordinates grow rightwards in x and downwards in y, while chunk keys grow
+// upwards, so chunk [0, 1] sits directly above chunk [0, 0].
+#[derive(Debug)]
+pub struct Universe {
+    pub map: HashMap<[i32; 2], Chunk>,
+    pub rule: Rule,
+    // A rule table that runs in place of `rule`, which then only records its
+    // number of states.
+    pub table: Option<Arc<RuleTable>>,
+    pub generation: u64,
+    pub topology: Topology,
+    // Worker threads used by `iterate`. Chunks are split into batches of at
+    // least `MIN_BATCH`, so small patterns stay on the calling thread.
+    pub threads: usize,
+    // The previous generation's map. It is emptied and refilled by the next
+    // step, so the table keeps its capacity instead of being rebuilt.
+    back: HashMap<[i32; 2], Chunk>,
+    revision: u64,
+}
+impl Universe {
+    pub fn new() -> Universe {
+        let mut map: HashMap<[i32; 2], Chunk> = HashMap::new();
+        map.insert([0, 0], Chunk::new([0, 0]));
+        Universe {
+            map,
+            rule: Rule::default(),
+            table: None,
+            generation: 0,
+            topology: Topology::Unbounded,
+            threads: Universe::default_threads(),
+            back: HashMap::new(),
+            revision: NEXT_REVISION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+    pub const MIN_BATCH: usize = 32;
+    // One thread per core.
+    pub fn default_threads() -> usize {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    }
+    pub fn from(chunk: [Row; SIZE]) -> Universe {
+        let mut universe = Universe::new();
+        universe.set_chunk([0, 0], chunk);
+        universe
+    }
+    // Changes whenever the universe does, and is kept by clones, so equal
+    // revisions mean equal contents.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+    // Gives the universe a new revision. Every method that changes it does
+    // this, so it is only needed after writing to the public fields.
+    pub fn touch(&mut self) {
+        self.revision = NEXT_REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn insert_chunk(&mut self, pos: [i32; 2], chunk: Chunk) {
+        self.touch();
+        let v = chunk.activations_within(self.rule.radius());
+        self.map.insert(pos, chunk);
+        self.activate(pos, v);
+    }
+    // Dying cells and colours are dropped when the number of states or
+    // colours changes, and a rule that reaches further makes sure the chunks
+    // it reaches exist.
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.touch();
+        if rule.states != self.rule.states || rule.colors != self.rule.colors {
+            for chunk in self.map.values_mut() {
+                chunk.states.clear();
+            }
+        }
+        let grows = rule.radius() > self.rule.radius();
+        self.rule = rule;
+        self.table = None;
+        if grows {
+            let activations: Vec<([i32; 2], u8)> = self
+                .map
+                .values()
+                .map(|chunk| (chunk.pos, chunk.activations_within(rule.radius())))
+                .collect();
+            for (pos, v) in activations {
+                self.activate(pos, v);
+            }
+        }
+    }
+    // Runs a rule table. One that amounts to a two-state outer-totalistic
+    // rule becomes that rule instead, which steps far faster.
+    pub fn set_table(&mut self, table: Arc<RuleTable>) {
+        if let Some(rule) = table.to_rule() {
+            self.set_rule(rule);
+            return;
+        }
+        self.set_rule(Rule {
+            states: table.states,
+            ..Rule::default()
+        });
+        self.table = Some(table);
+    }
+    pub fn set_chunk(&mut self, pos: [i32; 2], chunk: [Row; SIZE]) {
+        self.touch();
+        let v = {
+            let c = self.map.get_mut(&pos).unwrap();
+            c.set(chunk);
+            c.activations_within(self.rule.radius())
+        };
+        self.activate(pos, v);
+    }
+    // Makes sure the neighbours a chunk can grow into exist before the next step.
+    fn activate(&mut self, pos: [i32; 2], v: u8) {
+        let mut activations: Vec<[i32; 2]> = Vec::new();
+        Universe::interpret_activations(v, &mut activations, pos);
+        for i in activations {
+            if let Some(i) = self.topology.wrap_chunk(i) {
+                self.map.entry(i).or_insert_with(|| Chunk::new(i));
+            }
+        }
+    }
+    // Cells are moved into the world first; ones outside a bounded world that
+    // does not wrap are ignored.
+    pub fn set_topology(&mut self, topology: Topology) {
+        let cells = self.cells();
+        let states = self.states();
+        self.touch();
+        self.topology = topology;
+        self.map = HashMap::new();
+        self.map.insert([0, 0], Chunk::new([0, 0]));
+        for (x, y) in cells {
+            self.set_cell(x, y, true);
+        }
+        for (x, y, state) in states {
+            self.set_state(x, y, state);
+        }
+    }
+    // The chunk next to `pos` in direction (dx, dy), following the topology.
+    fn neighbour(&self, pos: [i32; 2], dx: i32, dy: i32) -> Option<&Chunk> {
+        let pos = self.topology.wrap_chunk([pos[0] + dx, pos[1] + dy])?;
+        self.map.get(&pos)
+    }
+    pub fn chunks(&mut self) -> Vec<&mut Chunk> {
+        self.touch();
+        self.map.values_mut().collect()
+    }
+    pub fn clear(&mut self) {
+        for chunk in self.chunks() {
+            chunk.set([0; SIZE]);
+            chunk.states.clear();
+        }
+    }
+    pub fn cell_pos(x: i32, y: i32) -> ([i32; 2], (u8, u8)) {
+        let size = SIZE as i32;
+        (
+            [x.div_euclid(size), -y.div_euclid(size)],
+            (x.rem_euclid(size) as u8, y.rem_euclid(size) as u8),
+        )
+    }
+    pub fn get_cell(&self, x: i32, y: i32) -> bool {
+        let (x, y) = match self.topology.wrap_cell(x, y) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        let (pos, bit) = Universe::cell_pos(x, y);
+        match self.map.get(&pos) {
+            Some(chunk) => chunk.get_bit_at_point((bit.0 as i8, bit.1 as i8)),
+            None => false,
+        }
+    }
+    pub fn set_cell(&mut self, x: i32, y: i32, val: bool) {
+        if self.get_cell(x, y) != val {
+            self.set_state(x, y, val as u8);
+        }
+    }
+    // Toggles between live and dead; a dying or coloured cell becomes dead.
+    pub fn toggle_cell(&mut self, x: i32, y: i32) {
+        let state = if self.get_state(x, y) == 0 { 1 } else { 0 };
+        self.set_state(x, y, state);
+    }
+    pub fn get_state(&self, x: i32, y: i32) -> u8 {
+        let (x, y) = match self.topology.wrap_cell(x, y) {
+            Some(cell) => cell,
+            None => return 0,
+        };
+        let (pos, bit) = Universe::cell_pos(x, y);
+        match self.map.get(&pos) {
+            Some(chunk) => chunk.state(bit),
+            None => 0,
+        }
+    }
+    pub fn set_state(&mut self, x: i32, y: i32, state: u8) {
+        let (x, y) = match self.topology.wrap_cell(x, y) {
+            Some(cell) => cell,
+            None => return,
+        };
+        self.touch();
+        let (pos, bit) = Universe::cell_pos(x, y);
+        let chunk = self.map.entry(pos).or_insert_with(|| Chunk::new(pos));
+        if self.rule.is_colored() && state > 0 {
+            chunk.set_color(bit, state - 1);
+        } else {
+            chunk.set_state(bit, state);
+        }
+        chunk.set_active(true);
+        self.activate(
+            pos,
+            Chunk::activations_near(bit.0, bit.1, self.rule.radius()),
+        );
+    }
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+        for chunk in self.map.values() {
+            let (left, top) = (chunk.pos[0] * SIZE as i32, -chunk.pos[1] * SIZE as i32);
+            for (y, &row) in chunk.chunk.iter().enumerate() {
+                let mut row = row;
+                while row != 0 {
+                    let x = SIZE as i32 - 1 - row.trailing_zeros() as i32;
+                    cells.push((left + x, top + y as i32));
+                    row &= row - 1;
+                }
+            }
+        }
+        cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        cells
+    }
+    // Cells in states above 1, with their state: dying cells, or live cells
+    // past the first colour.
+    pub fn states(&self) -> Vec<(i32, i32, u8)> {
+        let mut cells = Vec::new();
+        for chunk in self.map.values().filter(|chunk| !chunk.states.is_empty()) {
+            let (left, top) = (chunk.pos[0] * SIZE as i32, -chunk.pos[1] * SIZE as i32);
+            for y in 0..SIZE {
+                let mut row = chunk.states.iter().fold(0, |row, plane| row | plane[y]);
+                while row != 0 {
+                    let x = SIZE - 1 - row.trailing_zeros() as usize;
+                    let state = chunk.state((x as u8, y as u8));
+                    cells.push((left + x as i32, top + y as i32, state));
+                    row &= row - 1;
+                }
+            }
+        }
+        cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        cells
+    }
+    pub fn place(&mut self, pattern: &Pattern, offset: (i32, i32)) {
+        for &(x, y) in pattern.cells.iter() {
+            self.set_cell(x + offset.0, y + offset.1, true);
+        }
+        for &(x, y, state) in pattern.states.iter() {
+            self.set_state(x + offset.0, y + offset.1, state);
+        }
+    }
+    pub fn pattern(&self) -> Pattern {
+        Pattern {
+            cells: self.cells(),
+            states: self.states(),
+            rule: if self.table.is_some() {
+                None
+            } else {
+                Some(self.rule)
+            },
+            table: self.table.clone(),
+            ..Pattern::default()
+        }
+    }
+    // Every cell that isn't dead, dying ones included.
+    pub fn population(&self) -> usize {
+        self.map
+            .values()
+            .map(|chunk| {
+                (0..SIZE)
+                    .map(|y| chunk.occupied(y).count_ones() as usize)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+    pub fn edges(&self, pos: [i32; 2], corners: u8) -> Edges {
+        if self.topology.twists(pos) {
+            return self.halo_by_cell(pos);
+        }
+        Edges {
+            left: match self.neighbour(pos, -1, 0) {
+                Some(j) => j.right(),
+                None => 0,
+            },
+            right: match self.neighbour(pos, 1, 0) {
+                Some(j) => j.left(),
+                None => 0,
+            },
+            top: match self.neighbour(pos, 0, 1) {
+                Some(j) => j.bottom(),
+                None => 0,
+            },
+            bottom: match self.neighbour(pos, 0, -1) {
+                Some(j) => j.top(),
+                None => 0,
+            },
+            corners,
+        }
+    }
+    pub fn corners(&self, pos: [i32; 2]) -> u8 {
+        if self.topology.twists(pos) {
+            return self.halo_by_cell(pos).corners;
+        }
+        let chunks = [
+            self.neighbour(pos, -1, -1),
+            self.neighbour(pos, 1, -1),
+            self.neighbour(pos, -1, 1),
+            self.neighbour(pos, 1, 1),
+        ];
+        let last = SIZE as i8 - 1;
+        let points = [(last, 0), (0, 0), (last, last), (0, last)];
+        let mut int: u8 = 0;
+        for (i, (chunk, point)) in chunks.iter().zip(points.iter()).enumerate() {
+            if let Some(chunk) = chunk {
+                if chunk.get_bit_at_point(*point) {
+                    int |= 1 << (7 - i);
+                }
+            }
+        }
+        int
+    }
+    // The cells around a chunk, looked up one at a time so that mirrored
+    // neighbours across a twisted edge come out the right way round.
+    fn halo_by_cell(&self, pos: [i32; 2]) -> Edges {
+        let size = SIZE as i32;
+        let (x, y) = (pos[0] * size, -pos[1] * size);
+        let mut edges = Edges::default();
+        for i in 0..size {
+            let bit: Row = 1 << (size - 1 - i);
+            if self.get_cell(x - 1, y + i) {
+                edges.left |= bit;
+            }
+            if self.get_cell(x + size, y + i) {
+                edges.right |= bit;
+            }
+            if self.get_cell(x + i, y - 1) {
+                edges.top |= bit;
+            }
+            if self.get_cell(x + i, y + size) {
+                edges.bottom |= bit;
+            }
+        }
+        let corners = [
+            (x - 1, y + size),
+            (x + size, y + size),
+            (x - 1, y - 1),
+            (x + size, y - 1),
+        ];
+        for (i, &(cx, cy)) in corners.iter().enumerate() {
+            if self.get_cell(cx, cy) {
+                edges.corners |= 1 << (7 - i);
+            }
+        }
+        edges
+    }
+    // The live cells within `radius` of a chunk, copied from it and its eight
+    // neighbours, or one at a time next to a twisted edge. Under a rule table
+    // or a multi-colour rule every cell's state comes along.
+    fn halo(&self, pos: [i32; 2], radius: usize) -> Halo {
+        let mut halo = Halo::new(radius);
+        let (size, r) = (SIZE as i32, radius as i32);
+        let side = halo.side() as i32;
+        let states = self.table.is_some() || self.rule.is_colored();
+        if self.topology.twists(pos) {
+            let (x, y) = (pos[0] * size - r, -pos[1] * size - r);
+            for hy in 0..side {
+                for hx in 0..side {
+                    if self.get_cell(x + hx, y + hy) {
+                        halo.set(hx as usize, hy as usize);
+                    }
+                    if states {
+                        let state = self.get_state(x + hx, y + hy);
+                        halo.set_state(hx as usize, hy as usize, state);
+                    }
+                }
+            }
+            return halo;
+        }
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let chunk = match self.neighbour(pos, dx, dy) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+                // where the chunk's top left lands in the halo
+                let (left, top) = (dx * size + r, -dy * size + r);
+                for (y, &live) in chunk.chunk.iter().enumerate() {
+                    let hy = top + y as i32;
+                    if hy < 0 || hy >= side {
+                        continue;
+                    }
+                    let mut row = if states { chunk.occupied(y) } else { live };
+                    while row != 0 {
+                        let x = size - 1 - row.trailing_zeros() as i32;
+                        let hx = left + x;
+                        if hx >= 0 && hx < side {
+                            if live & (1 << row.trailing_zeros()) != 0 {
+                                halo.set(hx as usize, hy as usize);
+                            }
+                            if states {
+                                let state = chunk.state((x as u8, y as u8));
+                                halo.set_state(hx as usize, hy as usize, state);
+                            }
+                        }
+                        row &= row - 1;
+                    }
+                }
+            }
+        }
+        halo
+    }
+    // Steps every chunk, reading from `map` and writing into `back`, then
+    // swaps the two. The chunks are split into batches stepped on separate
+    // threads; each one only reads the current map and writes its own slot in
+    // the new one, so the result does not depend on the thread count.
+    pub fn iterate(&mut self) {
+        let mut next = std::mem::take(&mut self.back);
+        next.clear();
+        for pos in self.map.keys() {
+            next.insert(*pos, Chunk::new(*pos));
+        }
+        let mut targets: Vec<&mut Chunk> = next.values_mut().collect();
+        let workers = self.threads.min(targets.len() / Universe::MIN_BATCH).max(1);
+        let results = if workers == 1 {
+            self.step_batch(&mut targets)
+        } else {
+            let batch = targets.len().div_ceil(workers);
+            let universe = &*self;
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = targets
+                    .chunks_mut(batch)
+                    .map(|batch| scope.spawn(move || universe.step_batch(batch)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().unwrap())
+                    .collect()
+            })
+        };
+        let mut activations: Vec<[i32; 2]> = Vec::new();
+        for (pos, v, active) in results {
+            Universe::interpret_activations(v, &mut activations, pos);
+            if !active {
+                next.remove(&pos);
+            }
+        }
+        for i in activations {
+            if let Some(i) = self.topology.wrap_chunk(i) {
+                next.entry(i).or_insert_with(|| Chunk::new(i));
+            }
+        }
+        self.back = std::mem::replace(&mut self.map, next);
+        self.generation += 1;
+        self.touch();
+    }
+    // Steps the chunks at the targets' positions into the targets, returning
+    // each position with its activations and whether it stays active.
+    fn step_batch(&self, targets: &mut [&mut Chunk]) -> Vec<([i32; 2], u8, bool)> {
+        let mut results = Vec::with_capacity(targets.len());
+        for target in targets.iter_mut() {
+            let pos = target.pos;
+            let chunk = &self.map[&pos];
+            let v = if let Some(table) = &self.table {
+                let halo = self.halo(pos, 1);
+                chunk.step_table(&halo, table, target)
+            } else if self.rule.range.is_some() {
+                let halo = self.halo(pos, self.rule.radius());
+                chunk.step_range(&halo, &self.rule, target)
+            } else if self.rule.is_colored() {
+                let edges = self.edges(pos, self.corners(pos));
+                let halo = self.halo(pos, 1);
+                chunk.step_colored(&edges, &halo, &self.rule, target)
+            } else {
+                let edges = self.edges(pos, self.corners(pos));
+                chunk.step_into(&edges, &self.rule, target)
+            };
+            results.push((pos, v, target.active));
+        }
+        results
+    }
+    fn interpret_activations(v: u8, activations: &mut Vec<[i32; 2]>, pos: [i32; 2]) {
+        if Chunk::get_bit_at(v, 0) {
+            activations.push([pos[0] - 1, pos[1] + 1])
+        }
+        if Chunk::get_bit_at(v, 1) {
+            activations.push([pos[0], pos[1] + 1])
+        }
+        if Chunk::get_bit_at(v, 2) {
+            activations.push([pos[0] + 1, pos[1] + 1])
+        }
+        if Chunk::get_bit_at(v, 3) {
+            activations.push([pos[0] - 1, pos[1]])
+        }
+        if Chunk::get_bit_at(v, 4) {
+            activations.push([pos[0] + 1, pos[1]])
+        }
+        if Chunk::get_bit_at(v, 5) {
+            activations.push([pos[0] - 1, pos[1] - 1])
+        }
+        if Chunk::get_bit_at(v, 6) {
+            activations.push([pos[0], pos[1] - 1])
+        }
+        if Chunk::get_bit_at(v, 7) {
+            activations.push([pos[0] + 1, pos[1] - 1])
+        }
+    }
+}
+impl Engine for Universe {
+    fn get_cell(&self, x: i32, y: i32) -> bool {
+        Universe::get_cell(self, x, y)
+    }
+    fn set_cell(&mut self, x: i32, y: i32, val: bool) {
+        Universe::set_cell(self, x, y, val)
+    }
+    fn cells(&self) -> Vec<(i32, i32)> {
+        Universe::cells(self)
+    }
+    fn population(&self) -> u64 {
+        Universe::population(self) as u64
+    }
+    fn bounding_box(&self) -> Option<(i32, i32, i32, i32)> {
+        let cells = Universe::cells(self);
+        let first = cells.first()?;
+        let last = cells.last()?;
+        let min_x = cells.iter().map(|c| c.0).min()?;
+        let max_x = cells.iter().map(|c| c.0).max()?;
+        Some((min_x, first.1, max_x, last.1))
+    }
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+    fn step(&mut self, generations: u64) {
+        for _ in 0..generations {
+            self.iterate();
+        }
+    }
+}
+impl Clone for Universe {
+    fn clone(&self) -> Universe {
+        Universe {
+            map: self.map.clone(),
+            rule: self.rule,
+            table: self.table.clone(),
+            generation: self.generation,
+            topology: self.topology,
+            threads: self.threads,
+            back: HashMap::new(),
+            revision: self.revision,
+        }
+    }
+}
+impl Default for Universe {
+    fn default() -> Universe {
+        Universe::new()
+    }
+}
@@ -0,0 +1,166 @@
+use kiss3d::nalgebra::{Point2, Point3, Translation2};
+use kiss3d::scene::PlanarSceneNode;
+use kiss3d::window::Window;
+
+use crate::chunk::Chunk;
+use crate::chunk::SIZE;
+use crate::game::Game;
+
+// kiss3d renderer for a `Game`. It owns every scene node it creates and
+// rebuilds them from the universe on each draw.
+pub struct View {
+    nodes: Vec<PlanarSceneNode>,
+}
+impl View {
+    const COLORS: (Point3<f32>, Point3<f32>) =
+        (Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+    const HOVER_COLOR: Point3<f32> = Point3::new(0.0, 0.25, 0.0);
+    const BOUNDS_COLOR: Point3<f32> = Point3::new(0.5, 0.5, 0.5);
+    const SELECTION_COLOR: Point3<f32> = Point3::new(0.3, 0.6, 1.0);
+    const PASTE_COLOR: Point3<f32> = Point3::new(0.3, 0.45, 0.6);
+    // Live cells by colour under Immigration and QuadLife.
+    const CELL_COLORS: [Point3<f32>; 4] = [
+        Point3::new(1.0, 1.0, 1.0),
+        Point3::new(1.0, 0.3, 0.3),
+        Point3::new(0.3, 0.6, 1.0),
+        Point3::new(1.0, 0.85, 0.2),
+    ];
+    pub fn new() -> View {
+        View { nodes: Vec::new() }
+    }
+    pub fn draw(&mut self, game: &Game, window: &mut Window) {
+        self.remove_nodes(window);
+        for chunk in game.universe.map.values() {
+            self.draw_chunk(game, chunk, window);
+        }
+        if let Some(pos) = game.hover_chunk {
+            View::draw_outline(game, pos, &View::HOVER_COLOR, window);
+        }
+        if let Some(bounds) = game.universe.topology.bounds() {
+            View::draw_rect(game, bounds, &View::BOUNDS_COLOR, window);
+        }
+        if let Some(s) = game.selection {
+            let bounds = (s.left, s.top, s.right, s.bottom);
+            View::draw_rect(game, bounds, &View::SELECTION_COLOR, window);
+        }
+        if let Some(pattern) = &game.pasting {
+            let (x, y) = game.cursor;
+            let cells = pattern.all_states().into_iter();
+            self.draw_preview(game, cells.map(|cell| (cell.0 + x, cell.1 + y)), window);
+        }
+        self.draw_preview(game, game.preview().into_iter(), window);
+    }
+    // Cells about to be pasted or drawn.
+    fn draw_preview(
+        &mut self,
+        game: &Game,
+        cells: impl Iterator<Item = (i32, i32)>,
+        window: &mut Window,
+    ) {
+        for cell in cells {
+            let center = game.cell_center(cell);
+            let mut c = window.add_rectangle(game.bit_size, game.bit_size);
+            c.append_translation(&Translation2::new(center.0, center.1));
+            let color = View::PASTE_COLOR;
+            c.set_color(color.x, color.y, color.z);
+            self.nodes.push(c);
+        }
+    }
+    pub fn draw_chunk(&mut self, game: &Game, chunk: &Chunk, window: &mut Window) {
+        for y in 0..SIZE {
+            self.draw_byte(game, chunk, y, window);
+        }
+        if game.debug {
+            let color = if chunk.active {
+                &View::COLORS.1
+            } else {
+                &View::COLORS.0
+            };
+            View::draw_outline(game, chunk.pos, color, window);
+        }
+    }
+    pub fn draw_byte(&mut self, game: &Game, chunk: &Chunk, y: usize, window: &mut Window) {
+        let row = chunk.occupied(y);
+        if row == 0 {
+            return;
+        }
+        for x in 0..SIZE as u8 {
+            if Chunk::get_bit_in_row(row, x) {
+                let center = game.cell_center((
+                    chunk.pos[0] * SIZE as i32 + x as i32,
+                    -chunk.pos[1] * SIZE as i32 + y as i32,
+                ));
+                let mut c = window.add_rectangle(game.bit_size, game.bit_size);
+                c.append_translation(&Translation2::new(center.0, center.1));
+                let rule = &game.universe.rule;
+                let state = chunk.state((x, y as u8));
+                let table = game.universe.table.as_ref();
+                let color = if let Some(rgb) = table.and_then(|table| table.color(state)) {
+                    Point3::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32) / 255.0
+                } else if rule.is_colored() {
+                    View::CELL_COLORS[(state as usize - 1) % View::CELL_COLORS.len()]
+                } else {
+                    View::state_color(state, rule.states)
+                };
+                c.set_color(color.x, color.y, color.z);
+                self.nodes.push(c);
+            };
+        }
+    }
+    // Live cells are white; dying ones fade from yellow through red as they
+    // approach the last state.
+    fn state_color(state: u8, states: u8) -> Point3<f32> {
+        if state <= 1 {
+            return Point3::new(1.0, 1.0, 1.0);
+        }
+        let age = (state - 1) as f32 / (states.max(3) - 1) as f32;
+        Point3::new(1.0 - 0.6 * age, 0.9 * (1.0 - age), 0.0)
+    }
+    fn draw_outline(game: &Game, pos: [i32; 2], color: &Point3<f32>, window: &mut Window) {
+        let center = game.chunk_center(pos);
+        for x in 0..2 {
+            for y in 0..2 {
+                let a = (game.bit_size * SIZE as f32 / 2.0 - 0.25) * 2.0 * (x as f32 - 0.5);
+                let b = (game.bit_size * SIZE as f32 / 2.0 - 0.25) * 2.0 * (y as f32 - 0.5);
+                window.draw_planar_line(
+                    &Point2::new(a + center.0, a + center.1),
+                    &Point2::new(b + center.0, -b + center.1),
+                    color,
+                );
+            }
+        }
+    }
+    // Frames the cells from (left, top) to (right, bottom), such as those of
+    // a bounded world.
+    fn draw_rect(
+        game: &Game,
+        bounds: (i32, i32, i32, i32),
+        color: &Point3<f32>,
+        window: &mut Window,
+    ) {
+        let half = game.bit_size / 2.0;
+        let min = game.cell_center((bounds.0, bounds.1));
+        let max = game.cell_center((bounds.2, bounds.3));
+        let (left, top, right, bottom) = (min.0 - half, min.1 + half, max.0 + half, max.1 - half);
+        let corners = [
+            Point2::new(left, top),
+            Point2::new(right, top),
+            Point2::new(right, bottom),
+            Point2::new(left, bottom),
+        ];
+        for i in 0..4 {
+            window.draw_planar_line(&corners[i], &corners[(i + 1) % 4], color);
+        }
+    }
+    pub fn remove_nodes(&mut self, window: &mut Window) {
+        for node in self.nodes.iter_mut() {
+            window.remove_planar_node(node);
+        }
+        self.nodes.clear();
+    }
+}
+impl Default for View {
+    fn default() -> View {
+        View::new()
+    }
+}